@@ -1,6 +1,6 @@
 use std::{cmp::Ordering, ffi::{c_uchar, CStr, CString}};
 
-use instally_core::{definitions::{package::Package, summary::PackagePair}, helpers::{like::CStringLike, versioning::version_compare}, definitions::context::AppContext};
+use instally_core::{definitions::{bytebuffer::ByteBuffer, journal::InterruptedWorkload, package::Package, summary::PackagePair, verification::{FileIssue, PackageVerification}}, helpers::{like::CStringLike, versioning::version_compare}, definitions::context::AppContext};
 
 #[repr(C)]
 pub struct CallResult<T> {
@@ -35,25 +35,28 @@ pub struct CPackageVersioning {
     v_latest: *const i8,
     default: i32,
     state: i32,
+    release_notes: *const i8,
 }
 
 impl CPackageVersioning {
-    pub fn new(cross: &PackagePair) -> Self {
+    pub fn new(cross: &PackagePair, release_notes: &str) -> Self {
         CPackageVersioning {
             name: cross.local.name.as_c_char_ptr(),
             display_name: cross.local.display_name.as_c_char_ptr(),
             v_current: cross.local.version.as_c_char_ptr(),
             v_latest: cross.remote.version.as_c_char_ptr(),
             default: cross.remote.default as i32,
-            state: match version_compare(&cross.remote.version, &cross.local.version) {
+            // versions of mapped pairs are already validated by cross_check
+            state: match version_compare(&cross.remote.version, &cross.local.version).unwrap_or(Ordering::Equal) {
                 Ordering::Greater => 1,
                 Ordering::Less => -1,
                 Ordering::Equal => 0,
-            }
+            },
+            release_notes: release_notes.as_c_char_ptr(),
         }       
     }
 
-    pub fn new_not_installed(remote: &Package) -> Self {
+    pub fn new_not_installed(remote: &Package, release_notes: &str) -> Self {
         CPackageVersioning {
             name: remote.name.as_c_char_ptr(),
             display_name: remote.display_name.as_c_char_ptr(),
@@ -61,6 +64,7 @@ impl CPackageVersioning {
             v_latest: remote.version.as_c_char_ptr(),
             default: remote.default as i32,
             state: -2,
+            release_notes: release_notes.as_c_char_ptr(),
         }
     }
 
@@ -76,11 +80,78 @@ impl CPackageVersioning {
         unsafe { CStr::from_ptr(self.v_latest).to_str().unwrap().to_string() }
     }
 
+    pub fn get_release_notes(&self) -> String {
+        unsafe { CStr::from_ptr(self.release_notes).to_str().unwrap().to_string() }
+    }
+
     pub fn get_outdated(&self) -> bool {
         self.state == 1
     }
 }
 
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct CPackageVerification {
+    name: *const i8,
+    display_name: *const i8,
+    version: *const i8,
+    missing: i32,
+    modified: i32,
+    /// Paths relative to the package directory, buffers of null-terminated strings like the package name arguments
+    missing_files: *mut ByteBuffer,
+    modified_files: *mut ByteBuffer,
+}
+
+impl CPackageVerification {
+    pub fn new(verification: &PackageVerification) -> Self {
+        CPackageVerification {
+            name: verification.name.as_c_char_ptr(),
+            display_name: verification.display_name.as_c_char_ptr(),
+            version: verification.version.as_c_char_ptr(),
+            missing: verification.issues.iter().filter(|i| matches!(i, FileIssue::Missing(_))).count() as i32,
+            modified: verification.issues.iter().filter(|i| matches!(i, FileIssue::Modified(_))).count() as i32,
+            missing_files: files_of(verification, |i| matches!(i, FileIssue::Missing(_))),
+            modified_files: files_of(verification, |i| matches!(i, FileIssue::Modified(_))),
+        }
+    }
+
+    pub fn get_name(&self) -> String {
+        unsafe { CStr::from_ptr(self.name).to_str().unwrap().to_string() }
+    }
+
+    pub fn is_intact(&self) -> bool {
+        self.missing == 0 && self.modified == 0
+    }
+}
+
+/// Gets paths of the issues 'filter' accepts as a buffer of null-terminated strings
+fn files_of<F: Fn(&FileIssue) -> bool>(verification: &PackageVerification, filter: F) -> *mut ByteBuffer {
+    let paths = verification.issues.iter()
+        .filter(|issue| filter(issue))
+        .map(|issue| issue.get_path().to_string_lossy().into_owned().as_c_char_ptr())
+        .collect::<Vec<_>>();
+
+    Box::into_raw(Box::new(ByteBuffer::from_vec_struct(paths)))
+}
+
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct CInterruptedWorkload {
+    /// Kind of the workload, such as 'Installer'
+    workload: *const i8,
+    /// Number of changes it made before it was cut short
+    changes: i32,
+}
+
+impl CInterruptedWorkload {
+    pub fn new(interrupted: &InterruptedWorkload) -> Self {
+        CInterruptedWorkload {
+            workload: interrupted.workload.as_ref().as_c_char_ptr(),
+            changes: interrupted.steps.len() as i32,
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct CAppState { // TODO: caller side buffer
@@ -3,10 +3,10 @@
 mod macros;
 mod ffi;
 
-use std::sync::atomic::AtomicBool;
+use std::{ffi::{c_char, CStr}, sync::atomic::AtomicBool};
 
-use ffi::{CallResult, CPackageVersioning, CAppState};
-use instally_core::{definitions::{app::InstallyApp, bytebuffer::ByteBuffer, context::AppContextNotifiable, package::Package, product::Product, repository::Repository}, extensions::future::FutureSyncExt, factory::{self, WorkloadKind}, workloads::{installer::InstallerOptions, uninstaller::UninstallerOptions, updater::UpdaterOptions, workload::WorkloadResult}};
+use ffi::{CallResult, CPackageVersioning, CPackageVerification, CAppState, CInterruptedWorkload};
+use instally_core::{definitions::{app::InstallyApp, bytebuffer::ByteBuffer, context::AppContextNotifiable, package::Package, product::Product, repository::Repository}, extensions::future::FutureSyncExt, factory::{self, WorkloadKind}, workloads::{installer::InstallerOptions, repairer::RepairerOptions, uninstaller::UninstallerOptions, updater::UpdaterOptions, verifier::VerifierOptions, workload::WorkloadResult}};
 static ON_WORK: AtomicBool = std::sync::atomic::AtomicBool::new(false);
 
 pub struct Meta {
@@ -37,7 +37,24 @@ pub unsafe extern "C" fn init(m_packages: *mut ByteBuffer) {
 
 #[no_mangle] 
 pub unsafe extern "C" fn check_updates(m_packages: *mut ByteBuffer) -> *mut CallResult::<ByteBuffer> {  
+    check_updates_on_channel(m_packages, std::ptr::null())
+}
+
+/// Checks updates on release channel 'm_channel', the installed one if it's null
+#[no_mangle] 
+pub unsafe extern "C" fn check_updates_on_channel(m_packages: *mut ByteBuffer, m_channel: *const c_char) -> *mut CallResult::<ByteBuffer> {  
     let meta = Meta::get();
+    let channel = read_channel(m_channel).unwrap_or_else(|| meta.app.get_channel());
+    log::info!("Checking updates on release channel '{}'", channel);
+
+    let repository = match meta.app.get_channel_repository(&channel) {
+        Ok(repository) => repository,
+        Err(err) => {
+            log::error!("Update check failed, {:?}", err);
+            return CallResult::new(ByteBuffer::from_vec_struct(Vec::<CPackageVersioning>::new()), Some(&err.get_details().message)).into_raw();
+        }
+    };
+
     let packages = match m_packages {
         buff if buff.is_null() == false && (*buff).len() > 0 => {
             let packages = m_packages.read()
@@ -45,23 +62,33 @@ pub unsafe extern "C" fn check_updates(m_packages: *mut ByteBuffer) -> *mut Call
 
             log::info!("Checking updates, target package(s): {:?}", packages);   
 
-            meta.repository.packages.iter()
+            repository.packages.iter()
                 .filter(|f| packages.contains(&f.name))
                 .cloned().collect::<Vec<Package>>()
         },
         _ => {
             log::info!("Checking updates, target packages: all");
-            meta.repository.packages.clone()
+            repository.packages.clone()
         }
     };
 
-    let version_summary = meta.app.get_summary().cross_check(&packages);
+    let version_summary = match meta.app.get_summary().cross_check(&packages) {
+        Ok(version_summary) => version_summary,
+        Err(err) => {
+            log::error!("Update check failed, {:?}", err);
+            return CallResult::new(ByteBuffer::from_vec_struct(Vec::<CPackageVersioning>::new()), Some(&err.get_details().message)).into_raw();
+        }
+    };
+    // notes are fetched one package at a time, only packages with a newer version have any to show
     let mut c_arr  = version_summary.map.iter()
-        .map(|n| CPackageVersioning::new(n))
+        .map(|n| match version_summary.updates.iter().any(|u| u.remote.name == n.remote.name) {
+            true => CPackageVersioning::new(n, &release_notes(&meta.app, &n.remote, Some(&n.local.version))),
+            false => CPackageVersioning::new(n, ""),
+        })
         .collect::<Vec<_>>();
 
     version_summary.not_installed.iter().for_each(|n| {
-        c_arr.push(CPackageVersioning::new_not_installed(n));
+        c_arr.push(CPackageVersioning::new_not_installed(n, ""));
     });
 
     log::info!("Update check comlete, {}", version_summary);
@@ -70,13 +97,20 @@ pub unsafe extern "C" fn check_updates(m_packages: *mut ByteBuffer) -> *mut Call
 
 #[no_mangle]
 pub unsafe extern "C" fn apply_updates(m_packages: *mut ByteBuffer, state_callback: extern "C" fn(CAppState)) {
+    apply_updates_on_channel(m_packages, std::ptr::null(), state_callback)
+}
+
+/// Applies updates from release channel 'm_channel', switching the installation over to it. The installed channel is kept if it's null
+#[no_mangle]
+pub unsafe extern "C" fn apply_updates_on_channel(m_packages: *mut ByteBuffer, m_channel: *const c_char, state_callback: extern "C" fn(CAppState)) {
     let packages = m_packages.read().into_string_vec();
-    log::info!("Appliying update(s), target package(s) are: {:?}", packages);
+    let channel = read_channel(m_channel);
+    log::info!("Appliying update(s), target package(s) are: {:?}, channel: {:?}", packages, channel);
 
     let meta = Meta::get();
     let result = execute_blocking(
-        &meta.app.get_product(),
-        WorkloadKind::Updater(UpdaterOptions { target_packages: Some(packages) }),
+        &meta.app,
+        WorkloadKind::Updater(UpdaterOptions::new(Some(packages), channel)),
         state_callback
     );
 
@@ -94,8 +128,8 @@ pub unsafe extern "C" fn remove_package(m_packages: *mut ByteBuffer, state_callb
     let meta = Meta::get();
 
     let result = execute_blocking(
-        &meta.app.get_product(),
-        WorkloadKind::Uninstaller(UninstallerOptions { target_packages: Some(packages) }),
+        &meta.app,
+        WorkloadKind::Uninstaller(UninstallerOptions::new(Some(packages), false)),
         state_callback
     );
 
@@ -106,6 +140,7 @@ pub unsafe extern "C" fn remove_package(m_packages: *mut ByteBuffer, state_callb
 
 #[no_mangle]
 pub unsafe extern "C" fn install_package(m_packages: *mut ByteBuffer, state_callback: extern "C" fn(CAppState)) {
+    // package group names are accepted as well, they are expanded by the installer
     let packages = m_packages.read().into_string_vec();
     
     log::info!("Target packages are {:?}", packages);
@@ -113,7 +148,7 @@ pub unsafe extern "C" fn install_package(m_packages: *mut ByteBuffer, state_call
     let meta = Meta::get();
 
     let result = execute_blocking(
-        &meta.app.get_product(),
+        &meta.app,
         WorkloadKind::Installer(InstallerOptions { target_packages: Some(packages) }),
         state_callback
     );
@@ -124,15 +159,133 @@ pub unsafe extern "C" fn install_package(m_packages: *mut ByteBuffer, state_call
     }
 }
 
-fn execute_blocking(product_meta: &Product, settings: WorkloadKind, state_callback: extern "C" fn(CAppState)) -> Option<WorkloadResult> {
+#[no_mangle]
+pub unsafe extern "C" fn verify_packages(m_packages: *mut ByteBuffer) -> *mut CallResult::<ByteBuffer> {
+    let packages = match m_packages {
+        buff if buff.is_null() == false && (*buff).len() > 0 => Some(m_packages.read().into_string_vec()),
+        _ => None
+    };
+
+    log::info!("Verifying package(s), target package(s): {:?}", packages);
+
+    let meta = Meta::get();
+    let executor = factory::run(meta.app.clone(), WorkloadKind::Verifier(VerifierOptions::new(packages)), None);
+    let result = executor.runtime.block_on(executor.handle).unwrap();
+
+    let verification = executor.app.get_context().lock().get_verification();
+    let c_arr = verification.iter().map(CPackageVerification::new).collect::<Vec<_>>();
+
+    log::info!("Verify package operation complete, {}", result);
+    CallResult::new(ByteBuffer::from_vec_struct(c_arr), result.get_error().map(|err| err.message).as_deref()).into_raw()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn repair_packages(m_packages: *mut ByteBuffer, state_callback: extern "C" fn(CAppState)) {
+    let packages = match m_packages {
+        buff if buff.is_null() == false && (*buff).len() > 0 => Some(m_packages.read().into_string_vec()),
+        _ => None
+    };
+
+    log::info!("Repairing package(s), target package(s): {:?}", packages);
+
+    let meta = Meta::get();
+    let result = execute_blocking(
+        &meta.app,
+        WorkloadKind::Repairer(RepairerOptions::new(packages)),
+        state_callback
+    );
+
+    if let Some(result) = result {
+        log::info!("Repair package operation complete, {}", result);
+    }
+}
+
+/// Gets the workload that was cut short the last time, by a crash for instance. Null pointer if there is none.
+/// Unless it's resolved through 'resolve_interrupted_workload', it's rolled back once any other workload runs
+#[no_mangle]
+pub unsafe extern "C" fn get_interrupted_workload() -> *mut CInterruptedWorkload {
+    let meta = Meta::get();
+    match meta.app.get_interrupted_workload() {
+        Some(interrupted) => Box::into_raw(Box::new(CInterruptedWorkload::new(&interrupted))),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Resumes the interrupted workload, keeping the changes it made so far, or rolls them back
+#[no_mangle]
+pub unsafe extern "C" fn resolve_interrupted_workload(resume: bool, state_callback: extern "C" fn(CAppState)) {
+    if ON_WORK.load(std::sync::atomic::Ordering::Relaxed) {
+        return;
+    }
+
+    let meta = Meta::get();
+    let Some(interrupted) = meta.app.get_interrupted_workload() else {
+        log::info!("There is no interrupted workload to resolve.");
+        return;
+    };
+
+    if !resume {
+        match interrupted.rollback(&meta.app) {
+            Ok(workload) => log::info!("Rolled back interrupted {:?} workload.", workload),
+            Err(err) => log::error!("Failed to roll back the interrupted workload. {:?}", err),
+        }
+
+        return;
+    }
+
+    let workload = match interrupted.resume(&meta.app) {
+        Ok(workload) => workload,
+        Err(err) => {
+            log::error!("Failed to resume the interrupted workload. {:?}", err);
+            return;
+        }
+    };
+
+    if let Some(result) = execute_blocking(&meta.app, workload, state_callback) {
+        log::info!("Interrupted workload complete, {}", result);
+    }
+}
+
+/// Gets release notes of the versions between 'installed' and the one of 'package' as markdown.
+/// Notes are informative, failing to fetch them doesn't fail the update check
+fn release_notes(app: &InstallyApp, package: &Package, installed: Option<&str>) -> String {
+    match app.get_release_notes(package, installed).wait() {
+        Ok(notes) => notes.iter().map(|note| note.to_string()).collect::<Vec<_>>().join("\n\n"),
+        Err(err) => {
+            log::warn!("Failed to fetch release notes of {}, {:?}", package.name, err);
+            String::new()
+        }
+    }
+}
+
+/// Reads the release channel argument. Null pointer means the installed channel
+unsafe fn read_channel(m_channel: *const c_char) -> Option<String> {
+    if m_channel.is_null() {
+        return None;
+    }
+
+    Some(CStr::from_ptr(m_channel).to_string_lossy().into_owned())
+}
+
+fn execute_blocking(app: &InstallyApp, settings: WorkloadKind, state_callback: extern "C" fn(CAppState)) -> Option<WorkloadResult> {
     if ON_WORK.load(std::sync::atomic::Ordering::Relaxed) {
         return None;
     }
 
     ON_WORK.store(true, std::sync::atomic::Ordering::Relaxed);
 
-    let meta = Meta::get();
-    let executor = factory::run(meta.app.clone(), settings, None);
+    // leftovers of a workload cut short by a crash aren't recorded by the summary. unless it was resumed
+    // through 'resolve_interrupted_workload', it's rolled back before anything else runs
+    if let Some(interrupted) = app.get_interrupted_workload() {
+        log::warn!("Rolling back interrupted {:?} workload.", interrupted.workload);
+        if let Err(err) = interrupted.rollback(app) {
+            log::error!("Failed to roll back the interrupted workload. {:?}", err);
+            ON_WORK.store(false, std::sync::atomic::Ordering::Relaxed);
+            return None;
+        }
+    }
+
+    let executor = factory::run(app.clone(), settings, None);
 
     let sub_id = executor.app.get_context().lock().subscribe(Box::new(move |f| {
         state_callback(f.state_cloned.clone().into());
@@ -5,6 +5,7 @@ use std::io::SeekFrom::Start;
 use std::path::Path;
 
 use clap::Parser;
+use instally_core::archiving::bundle;
 use instally_core::definitions::product::Product;
 use instally_core::helpers::serializer;
 
@@ -14,6 +15,10 @@ pub struct Opt {
     // Configuration folder
     #[arg(short, long, default_value = "config")]
     config: String,
+
+    // Repository folder generated by repogen, appended to the setup binary for offline installations
+    #[arg(short, long)]
+    bundle: Option<String>,
 }
 
 fn main(){
@@ -34,7 +39,17 @@ fn inner(args: Opt) -> Result<(), Error> {
     let config_dir = Path::new(&args.config);
     let product_path = config_dir.join("product.json");
 
-    let tmp_product = Product::read_template(product_path).unwrap();
+    let mut tmp_product = Product::read_template(product_path).unwrap();
+
+    // setup refuses repositories it can't verify, a binary without the key would be of no use
+    let public_key = std::fs::read_to_string(config_dir.join("public.key"))
+        .map_err(|err| format!("Public key not found in configuration folder, generate one with repogen. {}", err))?;
+    if public_key.trim().is_empty() {
+        return Err(Error::new("Public key in configuration folder is empty."));
+    }
+
+    println!("Embedding public key for repository signature verification.");
+    tmp_product.public_key = public_key.trim().to_owned();
 
     std::fs::copy("instally-scaffold.exe", "Setup.exe")?;
     let file = std::cell::RefCell::new(OpenOptions::new()
@@ -58,6 +73,13 @@ fn inner(args: Opt) -> Result<(), Error> {
 
         file.borrow_mut().seek(Start((pos + query.len()) as u64)).unwrap();
         file.borrow_mut().write(&bytes)?; 
+        drop(file);
+
+        if let Some(bundle) = &args.bundle {
+            println!("Bundling repository at {:?}.", bundle);
+            bundle::append(Path::new("Setup.exe"), Path::new(bundle))
+                .map_err(|err| format!("Failed to bundle repository: {:?}", err))?;
+        }
 
         return Ok(());
     }
@@ -0,0 +1,193 @@
+use std::{fs::{File, OpenOptions}, io::{Read, Seek, SeekFrom, Write}, path::{Path, PathBuf}};
+
+use zip::{result::ZipError, ZipArchive};
+
+use crate::helpers::{self, file::IoError, tmp};
+
+use super::{error::ArchiveError, zip_write};
+
+/// Ends a setup binary that carries a repository. Preceded by the little endian length of the bundled archive
+pub const BUNDLE_MAGIC: &[u8] = b"###/BUNDLE/###";
+
+const TRAILER_SIZE: u64 = BUNDLE_MAGIC.len() as u64 + 8;
+
+/// A repository appended to the setup binary by binarygen. Its files are read from the setup binary in place
+/// as they are requested, nothing is extracted up front
+#[derive(Debug)]
+pub struct Bundle {
+    setup: PathBuf,
+    offset: u64,
+    length: u64,
+}
+
+impl Bundle {
+    /// Opens the repository bundled into the running executable. 'None' if there is no bundle
+    pub fn open_current() -> Result<Option<Self>, ArchiveError> {
+        let exe = std::env::current_exe().map_err(IoError::from)?;
+        Self::open(exe)
+    }
+
+    /// Opens the repository bundled into 'setup'. 'None' if there is no bundle
+    pub fn open<P: AsRef<Path>>(setup: P) -> Result<Option<Self>, ArchiveError> {
+        let mut file = helpers::file::open(&setup)?;
+        let Some((offset, length)) = find_archive(&mut file)? else {
+            return Ok(None);
+        };
+
+        let bundle = Bundle { setup: setup.as_ref().to_path_buf(), offset, length };
+        let archive = bundle.open_archive()?;
+
+        log::info!("Bundled repository with {} files found in {:?}", archive.len(), bundle.setup);
+        Ok(Some(bundle))
+    }
+
+    /// Copies the bundled file at 'path', relative to the repository root, to 'writer'. Returns false if it's not bundled
+    pub fn read_to<W: ?Sized + Write>(&self, path: &str, writer: &mut W) -> Result<bool, ArchiveError> {
+        let mut archive = self.open_archive()?;
+        let mut file = match archive.by_name(path.trim_start_matches('/')) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+
+        helpers::file::copy_stream(&mut file, writer)?;
+        Ok(true)
+    }
+
+    fn open_archive(&self) -> Result<ZipArchive<Section>, ArchiveError> {
+        let file = helpers::file::open(&self.setup)?;
+        Ok(ZipArchive::new(Section::new(file, self.offset, self.length))?)
+    }
+}
+
+/// Appends the repository at 'repository_dir', a repogen output, to 'setup'
+pub fn append<P: AsRef<Path>>(setup: P, repository_dir: P) -> Result<(), ArchiveError> {
+    let archive = tmp::create_tmp_file().map_err(IoError::from)?;
+
+    // archives are compressed already
+    zip_write::compress_dir(repository_dir.as_ref(), archive.path(), zip::CompressionMethod::Stored, None, false)?;
+
+    let mut setup = OpenOptions::new().append(true).open(setup).map_err(IoError::from)?;
+    let length = helpers::file::copy_stream(&mut helpers::file::open(archive.path())?, &mut setup)?;
+
+    helpers::file::write_all_stream(&mut setup, &length.to_le_bytes())?;
+    helpers::file::write_all_stream(&mut setup, BUNDLE_MAGIC)?;
+    Ok(())
+}
+
+/// Removes the repository bundled into 'setup', if there is one. Copies of the setup binary, such as the maintenance tool,
+/// would otherwise carry the repository along and keep installing from it
+pub fn strip<P: AsRef<Path>>(setup: P) -> Result<bool, ArchiveError> {
+    let mut file = OpenOptions::new().read(true).write(true).open(setup).map_err(IoError::from)?;
+    let Some((offset, _)) = find_archive(&mut file)? else {
+        return Ok(false);
+    };
+
+    file.set_len(offset).map_err(IoError::from)?;
+    Ok(true)
+}
+
+/// Gets the offset and length of the archive bundled into 'file', if there is one
+fn find_archive(file: &mut File) -> Result<Option<(u64, u64)>, IoError> {
+    let size = file.seek(SeekFrom::End(0))?;
+    if size < TRAILER_SIZE {
+        return Ok(None);
+    }
+
+    let mut trailer = [0; TRAILER_SIZE as usize];
+    file.seek(SeekFrom::Start(size - TRAILER_SIZE))?;
+    file.read_exact(&mut trailer)?;
+
+    let (length, magic) = trailer.split_at(8);
+    if magic != BUNDLE_MAGIC {
+        return Ok(None);
+    }
+
+    let length = u64::from_le_bytes(length.try_into().unwrap());
+    match (size - TRAILER_SIZE).checked_sub(length) {
+        Some(offset) => Ok(Some((offset, length))),
+        None => Ok(None),
+    }
+}
+
+/// Reads 'length' bytes of 'file' from 'offset' on as if they were a file of their own
+#[derive(Debug)]
+struct Section {
+    file: File,
+    offset: u64,
+    length: u64,
+    position: u64,
+}
+
+impl Section {
+    fn new(file: File, offset: u64, length: u64) -> Self {
+        Section { file, offset, length, position: 0 }
+    }
+}
+
+impl Read for Section {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.length.saturating_sub(self.position);
+        let size = buf.len().min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        if size == 0 {
+            return Ok(0);
+        }
+
+        self.file.seek(SeekFrom::Start(self.offset + self.position))?;
+        let read = self.file.read(&mut buf[..size])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for Section {
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(position) => Some(position),
+            SeekFrom::End(delta) => self.length.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+        };
+
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "seek to a negative position")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_and_find() {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = dir.path().join("repository");
+        std::fs::create_dir_all(repository.join("packages")).unwrap();
+        std::fs::write(repository.join("repository.json"), "{}").unwrap();
+        std::fs::write(repository.join("packages/core.zip"), "archive").unwrap();
+
+        let setup = dir.path().join("setup");
+        std::fs::write(&setup, "executable").unwrap();
+        assert!(Bundle::open(&setup).unwrap().is_none());
+
+        append(setup.as_path(), repository.as_path()).unwrap();
+
+        let (offset, length) = find_archive(&mut helpers::file::open(&setup).unwrap()).unwrap().unwrap();
+        assert_eq!(offset, "executable".len() as u64);
+        assert_eq!(offset + length + TRAILER_SIZE, std::fs::metadata(&setup).unwrap().len());
+
+        let bundle = Bundle::open(&setup).unwrap().unwrap();
+        let mut content = Vec::new();
+        assert!(bundle.read_to("packages/core.zip", &mut content).unwrap());
+        assert_eq!(content, b"archive");
+        assert!(!bundle.read_to("packages/missing.zip", &mut Vec::new()).unwrap());
+
+        assert!(strip(&setup).unwrap());
+        assert_eq!(std::fs::read(&setup).unwrap(), b"executable");
+        assert!(!strip(&setup).unwrap());
+    }
+}
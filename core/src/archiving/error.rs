@@ -1,5 +1,5 @@
 use convert_case::*;
-use helpers::{file::IoError, sha1::Sha1Error};
+use helpers::{checksum::ChecksumError, file::IoError};
 use zip::result::ZipError;
 use rust_i18n::error::*;
 
@@ -19,11 +19,11 @@ pub enum ArchiveError {
     #[error("invalid-password")]
     Password,
 
-    #[error("mismatching-sha1")]
-    Sha1Mismatch,
+    #[error("mismatching-checksum")]
+    ChecksumMismatch,
 
     #[error("{}", .0.get_message_key())]
-    Sha1(#[from] Sha1Error)
+    Checksum(#[from] ChecksumError)
 }
 
 impl From<ZipError> for ArchiveError {
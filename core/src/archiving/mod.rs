@@ -1,4 +1,5 @@
 
 pub mod zip_read;
 pub mod zip_write;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod bundle;
\ No newline at end of file
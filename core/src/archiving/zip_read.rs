@@ -2,37 +2,69 @@ use std::{fs::File, path::{self, Path}};
 
 use filepath::FilePath;
 
-use crate::helpers;
+use crate::helpers::{self, checksum::Checksum};
 
 use super::error::ArchiveError;
 
-pub fn extract_to<F>(input: &mut File, output: &Path, progress_callback: &F, sha1: Option<&str>) 
+/// What an extraction wrote, paths are relative to its output directory
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Extraction {
+    pub files: Vec<path::PathBuf>,
+    /// Directories that didn't exist before the extraction, parents first. An empty path stands for the output directory itself
+    pub directories: Vec<path::PathBuf>,
+}
+
+pub fn extract_to<F>(input: &mut File, output: &Path, progress_callback: &F, checksum: Option<&Checksum>) 
+    -> Result<Vec<path::PathBuf>, ArchiveError>
+where F: Fn(f32),
+{
+    Ok(extract_filtered(input, output, &|path| Some(path.to_path_buf()), progress_callback, checksum)?.files)
+}
+
+/// Extracts every entry to where 'redirect' maps its path, relative to 'output'
+pub fn extract_redirected_to<F, R>(input: &mut File, output: &Path, redirect: &R, progress_callback: &F, checksum: Option<&Checksum>)
+    -> Result<Extraction, ArchiveError>
+where F: Fn(f32), R: Fn(&Path) -> path::PathBuf,
+{
+    extract_filtered(input, output, &|path| Some(redirect(path)), progress_callback, checksum)
+}
+
+/// Extracts only the entries at 'files', paths relative to the archive root. Used to restore files of an installed package
+pub fn extract_files_to<F>(input: &mut File, output: &Path, files: &[path::PathBuf], progress_callback: &F, checksum: Option<&Checksum>)
     -> Result<Vec<path::PathBuf>, ArchiveError>
 where F: Fn(f32),
 {
-    if let Some(checksum) = sha1 {
-        let mut sha1 = String::new();
-        if !(helpers::sha1::verify_sha1_file(input, checksum, Some(&mut sha1))?) {
-            log::error!("Sha1 of downloaded package {:?} (sha: {:?}) does not match with {:?}", input.path(), sha1, checksum);
-            return Err(ArchiveError::Sha1Mismatch);
+    Ok(extract_filtered(input, output, &|path| files.iter().any(|f| f == path).then(|| path.to_path_buf()), progress_callback, checksum)?.files)
+}
+
+/// Extracts entries 'target' maps to a path, skipping the rest
+fn extract_filtered<F, P>(input: &mut File, output: &Path, target: &P, progress_callback: &F, checksum: Option<&Checksum>)
+    -> Result<Extraction, ArchiveError>
+where F: Fn(f32), P: Fn(&Path) -> Option<path::PathBuf>,
+{
+    if let Some(checksum) = checksum {
+        let (matches, actual) = checksum.verify_file(input)?;
+        if !matches {
+            log::error!("Checksum of downloaded package {:?} ({}) does not match with {}", input.path(), actual, checksum);
+            return Err(ArchiveError::ChecksumMismatch);
         }
     }
 
-    let mut paths = vec![];
+    let mut extraction = Extraction::default();
     let mut archive = zip::ZipArchive::new(input)?;
     let length = archive.len();
 
     for i in 0..length {
         let mut file = archive.by_index(i)?;
-        let outpath = match file.enclosed_name() {
-            Some(path) => path.to_owned(),
+        let outpath = match file.enclosed_name().and_then(|path| target(&path)) {
+            Some(path) => path,
             None => continue,
         };
         let outpath_full = output.join(outpath.clone());
 
         if (*file.name()).ends_with('/') {
             log::trace!("archive: file {} extracted to \"{}\"", i, outpath_full.display());
-            helpers::file::create_dir_all(&outpath_full)?;
+            create_dir_all(output, &outpath, &mut extraction.directories)?;
         } else {
             log::trace!(
                 "archive: file {} extracted to \"{}\" ({} bytes)",
@@ -40,14 +72,12 @@ where F: Fn(f32),
                 outpath_full.display(),
                 file.size()
             );
-            if let Some(p) = outpath_full.parent() {
-                if !p.exists() {
-                    helpers::file::create_dir_all(p)?;
-                }
+            if let Some(p) = outpath.parent() {
+                create_dir_all(output, p, &mut extraction.directories)?;
             }
             let mut outfile = helpers::file::create(&outpath_full)?;
             helpers::file::copy_stream(&mut file, &mut outfile)?;
-            paths.push(outpath.to_path_buf());
+            extraction.files.push(outpath.to_path_buf());
         }
 
         // Get and set permissions
@@ -65,5 +95,42 @@ where F: Fn(f32),
     }
 
     progress_callback(100.0);
-    Ok(paths)
-}
\ No newline at end of file
+    Ok(extraction)
+}
+
+/// Creates 'directory' under 'output' along with its missing parents, 'output' included. Records the ones it created in 'created'
+fn create_dir_all(output: &Path, directory: &Path, created: &mut Vec<path::PathBuf>) -> Result<(), ArchiveError> {
+    let mut missing = directory.ancestors()
+        .filter(|ancestor| !output.join(ancestor).is_dir())
+        .map(|ancestor| ancestor.to_path_buf())
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    helpers::file::create_dir_all(output.join(directory))?;
+
+    missing.reverse();
+    created.extend(missing);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_dir_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("package");
+        let mut created = vec![];
+
+        create_dir_all(&output, Path::new("a/b"), &mut created).unwrap();
+        create_dir_all(&output, Path::new("a/c"), &mut created).unwrap();
+        create_dir_all(&output, Path::new("a/b"), &mut created).unwrap();
+
+        assert!(output.join("a/b").is_dir());
+        assert_eq!(created, ["", "a", "a/b", "a/c"].map(path::PathBuf::from));
+    }
+}
@@ -3,7 +3,7 @@ use std::{io, path::{self, Path}};
 use walkdir::WalkDir;
 use zip::{write::SimpleFileOptions, ZipWriter};
 
-use crate::helpers::{self, sha1::Sha1Error};
+use crate::helpers::{self, checksum::Checksum};
 
 use super::error::ArchiveError;
 
@@ -50,7 +50,9 @@ where T: io::Write + io::Seek,
     Ok(paths)
 }
 
-pub fn compress_dir<P: AsRef<Path>>(src_dir: P, dst_file: P, method: zip::CompressionMethod, sha1: Option<&mut String>, writeout_sha1: bool) 
+/// Compresses 'src_dir' into 'dst_file'. When 'checksum' is supplied, its digest is filled using its algorithm
+/// and optionally written out as a side file next to the archive
+pub fn compress_dir<P: AsRef<Path>>(src_dir: P, dst_file: P, method: zip::CompressionMethod, checksum: Option<&mut Checksum>, writeout_checksum: bool) 
     -> Result<Vec<std::path::PathBuf>, ArchiveError> {
 
     if !src_dir.as_ref().is_dir() {
@@ -63,10 +65,12 @@ pub fn compress_dir<P: AsRef<Path>>(src_dir: P, dst_file: P, method: zip::Compre
     let it = walkdir.into_iter();
     let paths = compress_dirs(&mut it.filter_map(|e: Result<walkdir::DirEntry, walkdir::Error>| e.ok()), src_dir.as_ref().to_str().unwrap(), &mut file, method)?;
 
-    if let Some(out) = sha1 {
-        *out = helpers::sha1::generate_sha1_file(&mut file)?;
-        helpers::file::write_all(format!("{}.sha1", dst_file.as_ref().to_str().unwrap()), out.as_bytes())
-            .map_err(|err| Sha1Error::from(err))?;
+    if let Some(out) = checksum {
+        *out = Checksum::generate_file(out.algorithm, &mut file)?;
+
+        if writeout_checksum {
+            out.writeout(&dst_file)?;
+        }
     }
 
     Ok(paths)
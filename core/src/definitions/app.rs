@@ -1,37 +1,50 @@
-use std::{fmt::Display, path::Path, sync::Arc};
+use std::{cmp::Ordering, collections::HashMap, fmt::Display, path::{Path, PathBuf}, sync::Arc};
 
 use parking_lot::Mutex;
 use tokio::sync::OnceCell;
 
-use crate::{definitions::{dependency::{DependencyFile, PackageFile}, package::Package, product::Product, repository::Repository, script::Script, summary::InstallationSummary}, helpers::{self, file::IoError, tmp, workflow::Workflow}, http::client::{self, HttpStreamError}, workloads::{operations::{archive::ExtractArchiveOperation, createappentry::CreateAppEntryOperation, createfile::CreateFileOperation, createmaintinancetool::CreateMaintenanceToolOperation, createsymlink::CreateSymlinkOperation}, workload::WorkloadResult}};
+use crate::{archiving::{self, bundle::Bundle}, definitions::{changelog::{Changelog, ReleaseNote}, dependency::{DependencyFile, PackageFile}, package::Package, product::Product, repository::Repository, script::Script, summary::InstallationSummary, transaction::{self, Transaction}, verification::PackageVerification}, helpers::{self, disk, file::IoError, serializer::SerializationError, lock::{self, InstallationLock}, tmp, versioning::version_compare, workflow::Workflow}, http::{client::{self, HttpStreamError}, mirror::MirrorList}, workloads::{operations::{archive::ExtractArchiveOperation, createappentry::CreateAppEntryOperation, createfile::CreateFileOperation, createmaintinancetool::CreateMaintenanceToolOperation, createsymlink::CreateSymlinkOperation}, workload::WorkloadResult}};
 
-use super::{context::{AppContext, AppContextField}, error::{AppBuildError, PackageDownloadError, PackageInstallError, PackageUninstallError, RepositoryFetchError, ScriptError}, operation::{Operation, OperationHistory}, script::ScriptOptional, summary::PackageInstallation};
+use super::{context::{AppContext, AppContextField}, journal::{InterruptedWorkload, JournaledWorkload}, error::{AppBuildError, DiskSpaceError, PackageDownloadError, PackageInstallError, PackageRepairError, PackageUninstallError, RepositoryFetchError, ScriptError, SelfUpdateError}, operation::{Operation, OperationHistory, OperationKind, OperationPerformer, OperationRecord}, script::ScriptOptional, summary::PackageInstallation};
 
 #[derive(Clone, Debug)]
 pub struct InstallyApp {
     product: Product,
     repository: Arc<OnceCell<Repository>>,
+    mirrors: Arc<MirrorList>,
+    bundle: Option<Arc<Bundle>>,
+    transaction: Arc<Mutex<Option<Transaction>>>,
+    /// Configuration files the user changed, by package name. Left in place while the package is removed for an update
+    kept_config_files: Arc<Mutex<HashMap<String, Vec<PathBuf>>>>,
+    /// Held from the start of a mutating workload until it's committed or rolled back
+    lock: Arc<Mutex<Option<InstallationLock>>>,
     context: Arc<Mutex<AppContext>>,
 }
 
 impl Default for InstallyApp {
     fn default() -> Self {
-        Self { product: Default::default(), repository: Arc::new(OnceCell::new()), context: Arc::new(Mutex::new(AppContext::default())) }
+        Self { product: Default::default(), repository: Arc::new(OnceCell::new()), mirrors: Arc::new(MirrorList::default()), bundle: None, transaction: Arc::new(Mutex::new(None)), kept_config_files: Arc::new(Mutex::new(HashMap::new())), lock: Arc::new(Mutex::new(None)), context: Arc::new(Mutex::new(AppContext::default())) }
     }
 }
 
 impl InstallyApp
 {
     pub fn default_with_product(product: &Product) -> Self {
-        Self { product: product.clone(), repository: Arc::new(OnceCell::new()), context: Arc::new(Mutex::new(AppContext::default())) }
+        Self { product: product.clone(), repository: Arc::new(OnceCell::new()), mirrors: Arc::new(MirrorList::new(product.get_mirror_urls())), bundle: None, transaction: Arc::new(Mutex::new(None)), kept_config_files: Arc::new(Mutex::new(HashMap::new())), lock: Arc::new(Mutex::new(None)), context: Arc::new(Mutex::new(AppContext::default())) }
     }
 
-    /// Constructs the app meta without performing any network calls.
+    /// Constructs the app meta without performing any network calls. The installation is only locked once a mutating workload begins,
+    /// setup and maintenance tool fail early with 'AppBuildError::Lock' if another process is working on it already
     pub fn new(product: &Product) -> Result<Self, AppBuildError> {
         log::info!("Building InstallyApp meta");
 
         let workflow = helpers::workflow::define_workflow_env(&product)?;
 
+        // ffi api keeps answering queries meanwhile, its workloads fail once they begin instead
+        if workflow != Workflow::FfiApi {
+            lock::check(product.get_relative_target_directory())?;
+        }
+
         let summary = match workflow {
             Workflow::FreshInstallition => {
                 InstallationSummary::default(&product)
@@ -45,6 +58,11 @@ impl InstallyApp
             context: Arc::new(Mutex::new(AppContext::new(summary))),
             product: product.clone(),
             repository: Arc::new(OnceCell::new()),
+            mirrors: Arc::new(MirrorList::new(product.get_mirror_urls())),
+            bundle: None,
+            transaction: Arc::new(Mutex::new(None)),
+            kept_config_files: Arc::new(Mutex::new(HashMap::new())),
+            lock: Arc::new(Mutex::new(None)),
         })
     }
 
@@ -54,6 +72,20 @@ impl InstallyApp
         Ok(app)
     }
 
+    /// Installs from the repository in 'bundle', files missing in it are fetched from the remote repository
+    pub fn with_bundle(mut self, bundle: Bundle) -> Self {
+        log::info!("Using bundled repository, remote repository is only used for files missing in it.");
+        let bundle = Arc::new(bundle);
+        self.mirrors = Arc::new(MirrorList::with_bundle(bundle.clone(), self.product.get_mirror_urls()));
+        self.bundle = Some(bundle);
+        self
+    }
+
+    /// Gets the mirrors of the remote repository
+    pub fn get_mirrors(&self) -> &MirrorList {
+        &self.mirrors
+    }
+
     /// Gets app context
     pub fn get_context(&self) -> Arc<Mutex<AppContext>> {
         self.context.clone()
@@ -90,6 +122,20 @@ impl InstallyApp
             .expect("repository has not been fetched yet; call ensure_repository() first")
     }
 
+    /// Gets the release channel in use. The one recorded in the summary takes precedence over the product's
+    pub fn get_channel(&self) -> String {
+        self.get_summary().channel.clone()
+            .unwrap_or_else(|| self.product.channel.clone())
+    }
+
+    /// Gets the remote 'Repository' as seen from 'channel'.
+    ///
+    /// Panics if it has not been fetched yet by [`InstallyApp::ensure_repository`]
+    pub fn get_channel_repository(&self, channel: &str) -> Result<Repository, RepositoryFetchError> {
+        self.get_repository().get_channel(channel)
+            .ok_or_else(|| RepositoryFetchError::UnknownChannel(channel.to_owned()))
+    }
+
     /// Sets workload state
     pub fn set_workload_state<S: Display>(&self, n_state: S) {
         let mut ctx = self.context.lock(); 
@@ -102,17 +148,65 @@ impl InstallyApp
         ctx.update_field(AppContextField::state_progress(n_progress));
     }
 
+    /// Sets packages checked by the verify or repair workload
+    pub fn set_verification(&self, verification: Vec<PackageVerification>) {
+        let mut ctx = self.context.lock(); 
+        ctx.update_field(AppContextField::verification(verification))
+    }
+
     /// Gets workload result
     pub fn set_result(&self, result: &WorkloadResult) {
         let mut ctx = self.context.lock(); 
         ctx.update_field(AppContextField::result(Some(result.clone())))
     }
 
-    /// Fetches the remote repository on first call and caches it
+    /// Fetches the remote repository on first call and caches it.
+    ///
+    /// When a maintenance tool is too old to read the repository, it replaces itself with the installer published there.
+    /// A fresh setup is left as it is, it's a file the user downloaded and may run from anywhere. It fails with an error
+    /// suggesting to download the latest installer instead, so does the ffi api which is updated along with its host
     pub async fn ensure_repository(&self) -> Result<&Repository, RepositoryFetchError> {
-        self.repository
-            .get_or_try_init(|| self.product.fetch_repository())
-            .await
+        match self.repository.get_or_try_init(|| self.product.fetch_repository(&self.mirrors)).await {
+            Err(err) if err.requires_newer_installer() && helpers::workflow::get_workflow_from_env() == Workflow::MaintenanceTool => {
+                log::warn!("Repository requires a newer maintenance tool, updating it. {:?}", err);
+                match self.update_maintenance_tool().await {
+                    Ok(()) => Err(RepositoryFetchError::InstallerUpdated),
+                    Err(update_err) => {
+                        log::error!("Failed to update the maintenance tool. {:?}", update_err);
+                        Err(err)
+                    }
+                }
+            }
+            result => result,
+        }
+    }
+
+    /// Replaces the running executable with the installer published in the repository
+    pub async fn update_maintenance_tool(&self) -> Result<(), SelfUpdateError> {
+        let header = self.product.fetch_repository_header(&self.mirrors).await?;
+        let uri = self.product.get_uri_to_installer(&header).ok_or(SelfUpdateError::Unavailable)?;
+
+        let mut file = tmp::create_tmp_file().map_err(|err| IoError::from(err))?;
+        self.get_file(&uri, file.as_file_mut()).await?;
+
+        match &header.installer_checksum {
+            Some(checksum) => if !checksum.verify_file(file.as_file_mut())?.0 {
+                return Err(SelfUpdateError::MismatchingChecksum);
+            },
+            None => log::warn!("Installer has no checksum listed, its integrity won't be verified."),
+        }
+
+        self_replace::self_replace(file.path()).map_err(|err| IoError::from(err))?;
+        log::info!("Maintenance tool is replaced with the installer at {}", uri);
+        Ok(())
+    }
+
+    /// Checks that the download temp directory and the target directory have room for 'packages'.
+    /// Archives are downloaded and extracted one at a time, so the temp directory only needs to fit the largest one
+    pub fn ensure_disk_space(&self, packages: &[Package]) -> Result<(), DiskSpaceError> {
+        let temp = disk::get_volume(std::env::temp_dir());
+        let target = disk::get_volume(self.product.get_relative_target_directory());
+        check_disk_space(packages, temp.as_ref(), target.as_ref())
     }
 
     /// Downloads package file of specified package
@@ -120,8 +214,25 @@ impl InstallyApp
         let product = &self.product;  
         let mut file = tmp::create_tmp_file().map_err(|err| IoError::from(err))?;
         let _ = self.get_file(&product.get_uri_to_package(package), file.as_file_mut()).await?;
-        let sha1 = self.get_text(&product.get_uri_to_package_sha1(package)).await?;
-        Ok(PackageFile { handle: Arc::new(Mutex::new(file)), package: package.clone(), sha1 })
+
+        // checksum is taken from the signed repository rather than a side file served next to the archive
+        let checksum = package.get_checksum();
+        if checksum.is_none() {
+            log::warn!("Package {} has no checksum listed, its integrity won't be verified.", package.name);
+        }
+
+        Ok(PackageFile { handle: Arc::new(Mutex::new(file)), package: package.clone(), checksum })
+    }
+
+    /// Gets release notes of 'package' for every version newer than 'installed' up to its own, newest first
+    pub async fn get_release_notes(&self, package: &Package, installed: Option<&str>) -> Result<Vec<ReleaseNote>, HttpStreamError> {
+        match self.product.get_uri_to_package_release_notes(package) {
+            None => Ok(vec![]),
+            Some(uri) => {
+                let markdown = client::get_text_mirrored(&self.mirrors, &uri, |_| ()).await?;
+                Ok(Changelog::parse(&markdown).between(installed, &package.version))
+            }
+        }
     }
 
     /// Downloads installation script of specified package
@@ -184,12 +295,24 @@ impl InstallyApp
     /// Performs uninstallation for specified package installation
     pub async fn uninstall_package(&self, package_installation: &PackageInstallation) -> Result<(), PackageUninstallError> {
         let product = &self.product;
-        let package = self.get_repository().get_package(&package_installation.name).unwrap();
+        // replaced packages are no longer listed in the repository
+        let mut package = self.get_channel_repository(&self.get_channel()).ok()
+            .and_then(|repository| repository.get_package(&package_installation.name))
+            .unwrap_or_else(|| package_installation.to_package());
+        // files are reverted where they were extracted, even if a newer version moved the package
+        package.install_path = package_installation.install_path.clone();
+        package.config_files = package_installation.config_files.clone();
         let script = self.download_package_script(&package).await?;
 
         script.if_exist(|s| Ok(s.invoke_before_uninstallition()?))?;
 
-        package_installation.operations.get_records().into_iter().for_each(|record| {
+        // records might have changed since 'package_installation' was taken, such as directories handed over by packages removed before it
+        let operations = self.get_summary().get_packages().iter()
+            .find(|installation| installation.name == package_installation.name)
+            .map(|installation| installation.operations.clone())
+            .unwrap_or_else(|| package_installation.operations.clone());
+
+        operations.get_records().into_iter().for_each(|record| {
             if let Err(err) = record.into_operation(Some(&package)).and_then(|mut operation| operation.revert(&self, None)) {
                 log::error!("Failed to revert operation {:?}, included inside {} package. {:?}", record.get_kind(), package.display_name, err);
             }
@@ -204,16 +327,119 @@ impl InstallyApp
         Ok(())
     } 
 
+    /// Removes 'installation' ahead of installing another version of it. Configuration files the user changed are left
+    /// in place, the version installed next extracts its own next to them as '<file>.new'
+    pub async fn uninstall_package_for_update(&self, installation: &PackageInstallation) -> Result<(), PackageUninstallError> {
+        let package = installation.to_package();
+        let mut kept = Vec::new();
+        for record in installation.operations.get_records().iter().filter(|r| *r.get_kind() == OperationKind::ExtractArchiveOperation) {
+            let operation = ExtractArchiveOperation::from_record(Some(package.clone()), record)?;
+            kept.extend(operation.get_modified_config_files(self));
+        }
+
+        if !kept.is_empty() {
+            log::info!("Keeping changed configuration files of {}: {:?}", installation.display_name, kept);
+        }
+
+        self.keep_config_files(&installation.name, kept);
+        let result = self.uninstall_package(installation).await;
+        if result.is_err() {
+            self.kept_config_files.lock().remove(&installation.name);
+        }
+
+        result
+    }
+
+    /// Leaves configuration files 'files' of package 'name' in place while it's removed for an update
+    pub(crate) fn keep_config_files(&self, name: &str, files: Vec<PathBuf>) {
+        self.kept_config_files.lock().insert(name.to_owned(), files);
+    }
+
+    /// Gets the configuration files of package 'name' kept by its removal for an update
+    pub(crate) fn get_kept_config_files(&self, name: &str) -> Vec<PathBuf> {
+        self.kept_config_files.lock().get(name).cloned().unwrap_or_default()
+    }
+
+    /// Takes the configuration files of package 'name' kept by its removal for an update, once its new version is extracted
+    pub(crate) fn take_kept_config_files(&self, name: &str) -> Vec<PathBuf> {
+        self.kept_config_files.lock().remove(name).unwrap_or_default()
+    }
+
+    /// Checks files of 'installation' against what they were right after extraction
+    pub fn verify_package(&self, installation: &PackageInstallation) -> Result<PackageVerification, rust_i18n::error::Error> {
+        let package = installation.to_package();
+        let mut issues = Vec::new();
+
+        for record in installation.operations.get_records().iter().filter(|r| *r.get_kind() == OperationKind::ExtractArchiveOperation) {
+            let operation = ExtractArchiveOperation::from_record(Some(package.clone()), record)?;
+            issues.extend(operation.verify(self));
+        }
+
+        Ok(PackageVerification {
+            name: installation.name.clone(),
+            display_name: installation.display_name.clone(),
+            version: installation.version.clone(),
+            issues
+        })
+    }
+
+    /// Restores the missing and modified files of 'verification' from the archive of the installed version.
+    /// Nothing else is extracted, scripts don't run either. Files are extracted aside first and only replace
+    /// the installed ones once the archive yielded them cleanly.
+    ///
+    /// Fails with 'PackageRepairError::Unavailable' once the repository moved past the installed version, the package has to be updated then
+    pub async fn repair_package(&self, installation: &PackageInstallation, verification: &PackageVerification) -> Result<(), PackageRepairError> {
+        let mut package = self.get_channel_repository(&self.get_channel())?
+            .get_package(&installation.name)
+            .filter(|p| version_compare(&p.version, &installation.version).is_ok_and(|o| o == Ordering::Equal))
+            .ok_or_else(|| {
+                log::error!("Version {} of {} is no longer available to restore its files from, update it instead.", installation.version, installation.display_name);
+                PackageRepairError::Unavailable(installation.name.clone())
+            })?;
+
+        // files are restored where they were extracted, even if the repository moved the package since
+        package.install_path = installation.install_path.clone();
+        let package_directory = self.product.get_path_to_package(&package);
+        let files = verification.issues.iter().map(|issue| issue.get_path().to_path_buf()).collect::<Vec<_>>();
+
+        let package_file = self.download_package(&package).await?;
+
+        let staging = tempfile::tempdir().map_err(|err| rust_i18n::error::Error::from(IoError::from(err)))?;
+        let progress_closure = self.create_progress_closure();
+        let restored = {
+            let mut archive = package_file.handle.lock();
+            archiving::zip_read::extract_files_to(archive.as_file_mut(), staging.path(), &files, &progress_closure, package_file.checksum.as_ref())?
+        };
+
+        for file in &restored {
+            let target = package_directory.join(file);
+
+            // the damaged file is moved aside by the transaction, it's put back if the repair fails
+            match self.remove_file(&target) {
+                Err(SerializationError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => (),
+                result => result.map_err(rust_i18n::error::Error::from)?,
+            }
+
+            if let Some(parent) = target.parent() {
+                helpers::file::create_dir_all(parent).map_err(rust_i18n::error::Error::from)?;
+            }
+            helpers::file::move_file(staging.path().join(file), &target).map_err(rust_i18n::error::Error::from)?;
+        }
+
+        log::info!("Restored {} of {} file(s) of {}.", restored.len(), files.len(), package.display_name);
+        Ok(())
+    }
+
     /// Downloads the specified file
     pub async fn get_file(&self, url: &str, file: &mut std::fs::File) -> Result<(), HttpStreamError> {
         let progress_closure = self.create_progress_closure();
-        client::get_file(url, file, progress_closure).await
+        client::get_file_mirrored(&self.mirrors, url, file, progress_closure).await
     }
     
     /// Gets the specified text
     pub async fn get_text(&self, url: &str) -> Result<String, HttpStreamError> {
         let progress_closure = self.create_progress_closure();
-        client::get_text(url, progress_closure).await
+        client::get_text_mirrored(&self.mirrors, url, progress_closure).await
     }
 
     /// Creates a smylink
@@ -251,6 +477,102 @@ impl InstallyApp
         })
     }
 
+    /// Locks the installation and starts recording the changes 'workload' makes from now on, along with the current state of the summary.
+    /// Fails if another process is working on the installation
+    pub fn begin_transaction(&self, workload: JournaledWorkload) -> Result<(), rust_i18n::error::Error> {
+        self.lock_installation()?;
+
+        // a resumed workload carries on with the transaction it was interrupted in
+        let mut transaction = self.transaction.lock();
+        if transaction.as_ref().is_some_and(|transaction| transaction.is_of(&workload)) {
+            return Ok(());
+        }
+
+        *transaction = Some(Transaction::begin(workload, self.get_summary()));
+        Ok(())
+    }
+
+    /// Sets the transaction of an interrupted workload up to be carried on once the workload begins again
+    pub(crate) fn resume_transaction(&self, transaction: Transaction) {
+        *self.transaction.lock() = Some(transaction);
+    }
+
+    /// Keeps the changes made since the transaction began and releases the installation. Changes of the summary have to be persisted by then
+    pub fn commit_transaction(&self) {
+        let transaction = self.transaction.lock().take();
+        if let Some(transaction) = transaction {
+            transaction.commit();
+        }
+
+        self.unlock_installation();
+    }
+
+    /// Locks the installation against other processes until 'unlock_installation'. Setup, maintenance tool and ffi api would
+    /// otherwise change the same installation concurrently. The summary is read again, another process might have changed it meanwhile
+    pub fn lock_installation(&self) -> Result<(), AppBuildError> {
+        let mut lock = self.lock.lock();
+        if lock.is_some() {
+            return Ok(());
+        }
+
+        let acquired = lock::acquire(self.product.get_relative_target_directory())?;
+        self.modify_summary(|summary| summary.reload())?;
+
+        *lock = Some(acquired);
+        Ok(())
+    }
+
+    /// Releases the installation locked by 'lock_installation'
+    pub fn unlock_installation(&self) {
+        self.lock.lock().take();
+    }
+
+    /// Undoes the changes made since the transaction began and restores the summary. Does nothing if there is no transaction
+    pub fn rollback_transaction(&self) {
+        let transaction = self.transaction.lock().take();
+        if let Some(transaction) = transaction {
+            transaction.rollback(self);
+        }
+
+        self.unlock_installation();
+    }
+
+    /// Adds an executed operation to the ongoing transaction, if there is one
+    pub(crate) fn add_to_transaction(&self, target: Option<&Package>, record: OperationRecord) -> Result<(), rust_i18n::error::Error> {
+        if let Some(transaction) = self.transaction.lock().as_mut() {
+            transaction.add(target, record)?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the file at 'path'. During a transaction it's moved aside instead, to be put back if the workload fails
+    pub fn remove_file<P: AsRef<Path>>(&self, path: P) -> Result<(), SerializationError> {
+        match self.transaction.lock().as_mut() {
+            Some(transaction) => transaction.remove_file(path.as_ref()),
+            None => Ok(helpers::file::delete(path)?),
+        }
+    }
+
+    /// Removes the empty directory at 'path'. During a transaction it's created again if the workload fails
+    pub fn remove_dir<P: AsRef<Path>>(&self, path: P) -> Result<(), SerializationError> {
+        match self.transaction.lock().as_mut() {
+            Some(transaction) => transaction.remove_dir(path.as_ref()),
+            None => Ok(std::fs::remove_dir(path).map_err(IoError::from)?),
+        }
+    }
+
+    /// Gets the workload that was cut short the last time, by a power loss for instance. Its operations are journaled
+    /// but not recorded by the summary, it has to be either resumed or rolled back before anything else runs.
+    /// 'None' once it's resumed, the journal belongs to the ongoing transaction then
+    pub fn get_interrupted_workload(&self) -> Option<InterruptedWorkload> {
+        if self.transaction.lock().is_some() {
+            return None;
+        }
+
+        InterruptedWorkload::read(transaction::journal_path(&self.get_summary()))
+    }
+
     /// Persists changes made over the summary to the disk.
     pub fn persist_summary(&self) {
         self.modify_summary(|summary| {
@@ -258,3 +580,56 @@ impl InstallyApp
         });
     }
 }
+
+/// Checks 'packages' against the space available on the 'temp' and 'target' volumes. Unknown volumes aren't checked
+fn check_disk_space(packages: &[Package], temp: Option<&disk::Volume>, target: Option<&disk::Volume>) -> Result<(), DiskSpaceError> {
+    let temp_required = packages.iter().map(|p| p.size).max().unwrap_or(0);
+    let target_required = packages.iter().map(|p| p.installed_size).sum::<u64>();
+    log::info!("Required disk space, temp: {} bytes on {:?}, target: {} bytes on {:?}", temp_required, temp, target_required, target);
+
+    // both are counted against the same volume when they share it
+    let shared = matches!((temp, target), (Some(temp), Some(target)) if temp.mount_point == target.mount_point);
+
+    if let Some(temp) = temp {
+        let required = if shared { temp_required + target_required } else { temp_required };
+        if temp.available_space < required {
+            return Err(DiskSpaceError::InsufficientTemp(required, temp.available_space));
+        }
+    }
+
+    if let Some(target) = target {
+        if !shared && target.available_space < target_required {
+            return Err(DiskSpaceError::InsufficientTarget(target_required, target.available_space));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(mount_point: &str, available_space: u64) -> disk::Volume {
+        disk::Volume { mount_point: PathBuf::from(mount_point), available_space }
+    }
+
+    #[test]
+    fn test_check_disk_space() {
+        let packages = vec![
+            Package { name: "a".to_owned(), size: 100, installed_size: 300, ..Default::default() },
+            Package { name: "b".to_owned(), size: 200, installed_size: 500, ..Default::default() },
+        ];
+
+        // temp only has to fit the largest archive, target every extracted package
+        assert!(check_disk_space(&packages, Some(&volume("/tmp", 200)), Some(&volume("/opt", 800))).is_ok());
+        assert!(matches!(check_disk_space(&packages, Some(&volume("/tmp", 199)), Some(&volume("/opt", 800))), Err(DiskSpaceError::InsufficientTemp(200, 199))));
+        assert!(matches!(check_disk_space(&packages, Some(&volume("/tmp", 200)), Some(&volume("/opt", 799))), Err(DiskSpaceError::InsufficientTarget(800, 799))));
+
+        // a shared volume has to fit both
+        assert!(check_disk_space(&packages, Some(&volume("/", 1000)), Some(&volume("/", 1000))).is_ok());
+        assert!(matches!(check_disk_space(&packages, Some(&volume("/", 999)), Some(&volume("/", 999))), Err(DiskSpaceError::InsufficientTemp(1000, 999))));
+
+        assert!(check_disk_space(&packages, None, None).is_ok());
+    }
+}
@@ -0,0 +1,92 @@
+use std::{cmp::Ordering, fmt};
+
+use crate::helpers::versioning::{version_compare, Version};
+
+/// Notes of a single released version
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReleaseNote {
+    pub version: String,
+    pub text: String,
+}
+
+impl fmt::Display for ReleaseNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "## {}\n{}", self.version, self.text)
+    }
+}
+
+/// Release notes of a package, read from a markdown changelog where each version starts with a
+/// level two heading such as '## 1.2.0', '## v1.2.0' or '## [1.2.0] - 2024-01-15'
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Changelog {
+    pub notes: Vec<ReleaseNote>,
+}
+
+impl Changelog {
+    /// Parses a markdown changelog. Text before the first version heading is ignored
+    pub fn parse(markdown: &str) -> Self {
+        let mut notes = Vec::<ReleaseNote>::new();
+
+        for line in markdown.lines() {
+            match line.strip_prefix("## ") {
+                Some(heading) => {
+                    let heading = heading.split_whitespace().next().unwrap_or_default()
+                        .trim_matches(|c| c == '[' || c == ']');
+
+                    // spelled the way package versions are, 'v1.2.0' reads as '1.2.0'
+                    let version = Version::parse(heading).map(|version| version.to_string()).unwrap_or(heading.to_owned());
+                    notes.push(ReleaseNote { version, text: String::new() });
+                }
+                None => if let Some(note) = notes.last_mut() {
+                    note.text.push_str(line);
+                    note.text.push('\n');
+                }
+            }
+        }
+
+        notes.iter_mut().for_each(|note| note.text = note.text.trim().to_owned());
+        Changelog { notes }
+    }
+
+    /// Builds a changelog of a single version
+    pub fn from_note(version: &str, text: &str) -> Self {
+        Changelog { notes: vec![ReleaseNote { version: version.to_owned(), text: text.trim().to_owned() }] }
+    }
+
+    /// Gets notes of the versions newer than 'installed' up to and including 'latest', newest first.
+    /// Every version up to 'latest' is included if nothing is installed. Notes with unreadable versions are skipped
+    pub fn between(&self, installed: Option<&str>, latest: &str) -> Vec<ReleaseNote> {
+        let mut notes = self.notes.iter()
+            .filter(|note| version_compare(&note.version, latest).is_ok_and(|o| o != Ordering::Greater))
+            .filter(|note| match installed {
+                Some(installed) => version_compare(&note.version, installed).is_ok_and(|o| o == Ordering::Greater),
+                None => true,
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        notes.sort_by(|a, b| version_compare(&b.version, &a.version).unwrap_or(Ordering::Equal));
+        notes
+    }
+
+    /// Writes the changelog back to markdown
+    pub fn to_markdown(&self) -> String {
+        self.notes.iter().map(|note| note.to_string()).collect::<Vec<_>>().join("\n\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_between() {
+        let changelog = Changelog::parse("# Changelog\n\n## [1.2.0] - 2024-02-01\n- fixed a crash\n\n## v1.1.0\n- new icons\n\n## 1.0.0\n- initial release\n");
+        assert_eq!(changelog.notes.len(), 3);
+        assert_eq!(changelog.notes[0], ReleaseNote { version: "1.2.0".into(), text: "- fixed a crash".into() });
+
+        let notes = changelog.between(Some("1.0.0"), "1.2.0");
+        assert_eq!(notes.iter().map(|n| n.version.as_str()).collect::<Vec<_>>(), ["1.2.0", "1.1.0"]);
+        assert_eq!(changelog.between(None, "1.1.0").len(), 2);
+    }
+}
@@ -5,7 +5,7 @@ use parking_lot::Mutex;
 
 use crate::workloads::workload::WorkloadResult;
 
-use super::{app::InstallyApp, summary::InstallationSummary};
+use super::{app::InstallyApp, summary::InstallationSummary, verification::PackageVerification};
 
 pub type ArcM<T> = Arc<Mutex<T>>;
 pub type LazyArcM<T> = Lazy<ArcM<T>>;
@@ -38,7 +38,10 @@ pub struct AppContext {
     state: Option<String>,
     state_progress: f32,
     result: Option<WorkloadResult>,
-    summary: InstallationSummary
+    summary: InstallationSummary,
+
+    /// Packages checked by the last verify or repair workload
+    verification: Vec<PackageVerification>,
 }
 
 impl AppContextNotifiable for AppContext {
@@ -68,7 +71,8 @@ impl AppContext
             state_progress: 0.0,
             state: None,
             result: None,
-            summary
+            summary,
+            verification: Vec::new(),
         }
     }
 
@@ -112,6 +116,11 @@ impl AppContext
         self.state_progress
     }  
 
+    /// Gets packages checked by the last verify or repair workload
+    pub fn get_verification(&self) -> Vec<PackageVerification> {
+        self.verification.clone()
+    }
+
     /// Retreives immutable, cloned instance of 'InstallationSummary'
     pub fn get_summary(&self) -> InstallationSummary {
         self.summary.clone()
@@ -2,6 +2,8 @@ use std::{process::Command, sync::Arc};
 
 use parking_lot::Mutex;
 
+use crate::helpers::checksum::Checksum;
+
 use super::package::Package;
 
 
@@ -9,7 +11,7 @@ use super::package::Package;
 #[derive(Clone, Debug)]
 pub struct PackageFile {
     pub handle: Arc<Mutex<tempfile::NamedTempFile>>,
-    pub sha1: String,
+    pub checksum: Option<Checksum>,
     pub package: Package
 }
 
@@ -1,5 +1,5 @@
 use crate::*;
-use crate::{http::client::HttpStreamError, scripting::error::IJSError, helpers::serializer::SerializationError};
+use crate::{archiving::error::ArchiveError, http::client::HttpStreamError, scripting::error::IJSError, helpers::{checksum::ChecksumError, lock::LockError, serializer::SerializationError, signature::SignatureError, versioning::VersionError}};
 
 use helpers::file::IoError;
 use rust_i18n::error::*;
@@ -15,6 +15,9 @@ pub enum AppBuildError {
 
     #[error("{}", .0.get_message_key())]
     Io(#[from] IoError),
+
+    #[error("{}", .0.get_message_key())]
+    Lock(#[from] LockError),
 }
 
 #[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
@@ -24,6 +27,52 @@ pub enum RepositoryFetchError {
 
     #[error("{}", .0.get_message_key())]
     Serialization(#[from] SerializationError),
+
+    #[error("unknown-channel")]
+    UnknownChannel(String),
+
+    #[error("{}", .0.get_message_key())]
+    Signature(#[from] SignatureError),
+
+    #[error("unsupported-schema")]
+    UnsupportedSchema(u32),
+
+    #[error("outdated-installer")]
+    OutdatedInstaller(String),
+
+    #[error("installer-updated")]
+    InstallerUpdated,
+
+    #[error("{}", .0.get_message_key())]
+    Version(#[from] VersionError),
+}
+
+impl RepositoryFetchError {
+    /// Gets whether the repository can only be read by a newer installer
+    pub fn requires_newer_installer(&self) -> bool {
+        matches!(self, RepositoryFetchError::UnsupportedSchema(_) | RepositoryFetchError::OutdatedInstaller(_))
+    }
+}
+
+#[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
+pub enum SelfUpdateError {
+    #[error("{}", .0.get_message_key())]
+    Repository(#[from] RepositoryFetchError),
+
+    #[error("{}", .0.get_message_key())]
+    HttpStream(#[from] HttpStreamError),
+
+    #[error("{}", .0.get_message_key())]
+    Checksum(#[from] ChecksumError),
+
+    #[error("{}", .0.get_message_key())]
+    Io(#[from] IoError),
+
+    #[error("unavailable")]
+    Unavailable,
+
+    #[error("mismatching-checksum")]
+    MismatchingChecksum,
 }
 
 #[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
@@ -44,17 +93,83 @@ pub enum ScriptError {
     IJS(#[from] IJSError),
 }
 
+#[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
+pub enum DependencyError {
+    #[error("invalid-requirement")]
+    InvalidRequirement(String),
+
+    #[error("missing")]
+    Missing(String, String),
+
+    #[error("unsatisfied")]
+    Unsatisfied(String, String, String),
+
+    #[error("conflict")]
+    Conflict(String, String),
+
+    #[error("cycle")]
+    Cycle(Vec<String>),
+
+    #[error("required-by")]
+    RequiredBy(String, Vec<String>),
+
+    #[error("{}", .0.get_message_key())]
+    Version(#[from] VersionError),
+}
+
+#[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
+pub enum DiskSpaceError {
+    #[error("insufficient-temp")]
+    InsufficientTemp(u64, u64),
+
+    #[error("insufficient-target")]
+    InsufficientTarget(u64, u64),
+}
+
 #[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
 pub enum PackageInstallError {
     #[error("{}", .0.get_message_key())]
     Repository(#[from] RepositoryFetchError),
 
+    #[error("{}", .0.get_message_key())]
+    Dependency(#[from] DependencyError),
+
+    #[error("{}", .0.get_message_key())]
+    Version(#[from] VersionError),
+
+    #[error("{}", .0.get_message_key())]
+    DiskSpace(#[from] DiskSpaceError),
+
     #[error("{}", .0.get_message_key())]
     Script(#[from] ScriptError),
 
     #[error("{}", .0.get_message_key())]
     Package(#[from] PackageDownloadError), 
 
+    #[error("{}", .0.get_message_key())]
+    Uninstall(#[from] PackageUninstallError),
+
+    #[error("{}", .0.get_details().fullname)]
+    Other(#[from] rust_i18n::error::Error)
+}
+
+#[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
+pub enum PackageRepairError {
+    #[error("unavailable")]
+    Unavailable(String),
+
+    #[error("installation-not-found")]
+    InstallationNotFound(String),
+
+    #[error("{}", .0.get_message_key())]
+    Repository(#[from] RepositoryFetchError),
+
+    #[error("{}", .0.get_message_key())]
+    Package(#[from] PackageDownloadError),
+
+    #[error("{}", .0.get_message_key())]
+    Archive(#[from] ArchiveError),
+
     #[error("{}", .0.get_details().fullname)]
     Other(#[from] rust_i18n::error::Error)
 }
@@ -64,9 +179,15 @@ pub enum PackageUninstallError {
     #[error("installition-not-found")]
     InstallationNotFound,
 
+    #[error("required")]
+    Required(String),
+
     #[error("{}", .0.get_message_key())]
     Repository(#[from] RepositoryFetchError),
 
+    #[error("{}", .0.get_message_key())]
+    Dependency(#[from] DependencyError),
+
     #[error("{}", .0.get_message_key())]
     Script(#[from] ScriptError),
 
@@ -79,6 +200,15 @@ pub enum PackageUpdateError {
     #[error("{}", .0.get_message_key())]
     Repository(#[from] RepositoryFetchError),
 
+    #[error("{}", .0.get_message_key())]
+    Dependency(#[from] DependencyError),
+
+    #[error("{}", .0.get_message_key())]
+    Version(#[from] VersionError),
+
+    #[error("{}", .0.get_message_key())]
+    DiskSpace(#[from] DiskSpaceError),
+
     #[error("{}", .0.get_message_key())]
     Script(#[from] ScriptError),
 
@@ -0,0 +1,191 @@
+use std::{fs::File, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{factory::WorkloadKind, helpers::{self, file::IoError, serializer::{self, SerializationError}}, workloads::{installer::InstallerOptions, repairer::RepairerOptions, uninstaller::UninstallerOptions, updater::UpdaterOptions}};
+
+use super::{app::InstallyApp, transaction::{self, Transaction, TransactionStep}};
+
+/// Journal of the running workload, kept next to 'instally_summary.json'
+pub const JOURNAL_FILE_NAME: &str = "instally_journal.jsonl";
+
+/// Workload a journal is kept for, along with what's needed to start it over
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, strum::AsRefStr)]
+#[serde(rename_all = "PascalCase", rename_all_fields = "PascalCase")]
+pub enum JournaledWorkload {
+    Installer { target_packages: Option<Vec<String>> },
+    Updater { target_packages: Option<Vec<String>>, channel: Option<String> },
+    Uninstaller { target_packages: Option<Vec<String>>, cascade: bool },
+    Repairer { target_packages: Option<Vec<String>> },
+}
+
+impl JournaledWorkload {
+    /// Gets the workload to run in order to start it over
+    pub fn into_workload_kind(self) -> WorkloadKind {
+        match self {
+            JournaledWorkload::Installer { target_packages } => WorkloadKind::Installer(InstallerOptions::new(target_packages)),
+            JournaledWorkload::Updater { target_packages, channel } => WorkloadKind::Updater(UpdaterOptions::new(target_packages, channel)),
+            JournaledWorkload::Uninstaller { target_packages, cascade } => WorkloadKind::Uninstaller(UninstallerOptions::new(target_packages, cascade)),
+            JournaledWorkload::Repairer { target_packages } => WorkloadKind::Repairer(RepairerOptions::new(target_packages)),
+        }
+    }
+}
+
+/// A line of the journal
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+enum JournalEntry {
+    Begin(JournaledWorkload),
+    Step(TransactionStep),
+}
+
+/// Write-ahead journal of a running workload. The summary is only persisted once the workload completes,
+/// so every change is appended and synced to the disk as soon as it's made instead
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+impl Journal {
+    /// Creates the journal at 'path' for 'workload', replacing any existing one
+    pub fn create<P: AsRef<Path>>(path: P, workload: &JournaledWorkload) -> Result<Self, SerializationError> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            helpers::file::create_dir_all(parent)?;
+        }
+
+        let file = helpers::file::create(&path)?;
+        let mut journal = Journal { path, file };
+        journal.write(&JournalEntry::Begin(workload.clone()))?;
+        Ok(journal)
+    }
+
+    /// Rewrites the journal at 'path' for an interrupted 'workload' that's carried on, keeping its 'steps'.
+    /// An entry cut short by a crash is dropped so that new ones can be appended
+    pub fn resume<P: AsRef<Path>>(path: P, workload: &JournaledWorkload, steps: &[TransactionStep]) -> Result<Self, SerializationError> {
+        let path = path.as_ref().to_path_buf();
+        let content = std::iter::once(JournalEntry::Begin(workload.clone()))
+            .chain(steps.iter().cloned().map(JournalEntry::Step))
+            .map(|entry| serde_json::to_string(&entry).map(|line| format!("{}\n", line)))
+            .collect::<Result<String, _>>()?;
+
+        helpers::file::write_atomic(&path, content.as_bytes(), None)?;
+        let file = helpers::file::open_append(&path)?;
+        Ok(Journal { path, file })
+    }
+
+    /// Appends a change made by the workload
+    pub fn append(&mut self, step: &TransactionStep) -> Result<(), SerializationError> {
+        self.write(&JournalEntry::Step(step.clone()))
+    }
+
+    /// Removes the journal, the workload is either persisted or rolled back
+    pub fn remove(self) -> Result<(), IoError> {
+        drop(self.file);
+        helpers::file::delete(&self.path)
+    }
+
+    fn write(&mut self, entry: &JournalEntry) -> Result<(), SerializationError> {
+        let line = serde_json::to_string(entry)?;
+        helpers::file::write_all_file(&mut self.file, format!("{}\n", line).as_bytes())?;
+        self.file.sync_data().map_err(IoError::from)?;
+        Ok(())
+    }
+}
+
+/// A workload that didn't complete, read back from the journal it left behind.
+/// None of its changes made it into the summary
+#[derive(Clone, Debug)]
+pub struct InterruptedWorkload {
+    pub workload: JournaledWorkload,
+    pub steps: Vec<TransactionStep>,
+    path: PathBuf,
+}
+
+impl InterruptedWorkload {
+    /// Reads the journal at 'path'. 'None' if there is no journal. An entry cut short by a crash ends the journal
+    pub fn read<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let content = helpers::file::read_to_string(path.as_ref()).ok()?;
+        let mut entries = content.lines()
+            .map_while(|line| serializer::from_json::<JournalEntry>(line).ok());
+
+        let Some(JournalEntry::Begin(workload)) = entries.next() else {
+            log::warn!("Journal {:?} has no readable header, ignoring it.", path.as_ref());
+            return None;
+        };
+
+        let steps = entries
+            .filter_map(|entry| match entry {
+                JournalEntry::Step(step) => Some(step),
+                JournalEntry::Begin(_) => None,
+            })
+            .collect();
+
+        Some(InterruptedWorkload { workload, steps, path: path.as_ref().to_path_buf() })
+    }
+
+    /// Carries on with the workload, keeping the journaled changes. They are undone along with the new ones should it fail again.
+    /// Gets the workload to run. Fails if another process is working on the installation
+    pub fn resume(self, app: &InstallyApp) -> Result<WorkloadKind, rust_i18n::error::Error> {
+        app.lock_installation()?;
+
+        log::info!("Resuming interrupted {:?} workload, keeping {} change(s).", self.workload, self.steps.len());
+        let transaction = Transaction::resume(self.workload.clone(), app.get_summary(), self.steps)
+            .inspect_err(|_| app.unlock_installation())?;
+
+        app.resume_transaction(transaction);
+        Ok(self.workload.into_workload_kind())
+    }
+
+    /// Undoes the journaled changes, latest first, and removes the journal along with the files moved aside.
+    /// Gets the workload back so that it can be started over. Fails if another process is working on the installation
+    pub fn rollback(self, app: &InstallyApp) -> Result<JournaledWorkload, rust_i18n::error::Error> {
+        app.lock_installation()?;
+
+        log::info!("Rolling back interrupted {:?} workload, {} change(s).", self.workload, self.steps.len());
+        transaction::undo_steps(app, &self.steps);
+        transaction::remove_backup_directory(&app.get_summary());
+
+        if let Err(err) = helpers::file::delete(&self.path) {
+            log::error!("Failed to remove journal {:?}. {:?}", self.path, err);
+        }
+
+        app.unlock_installation();
+        Ok(self.workload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::definitions::operation::{OperationKind, OperationRecord};
+
+    use super::*;
+
+    #[test]
+    fn test_read_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(JOURNAL_FILE_NAME);
+        let workload = JournaledWorkload::Installer { target_packages: Some(vec!["core".into()]) };
+        let step = TransactionStep::Operation { package: None, record: OperationRecord::new(OperationKind::CreateFileOperation, "{}".into()) };
+
+        let mut journal = Journal::create(&path, &workload).unwrap();
+        journal.append(&step).unwrap();
+        journal.append(&step).unwrap();
+
+        // power loss while the last entry was being written
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, &content[..content.len() - 10]).unwrap();
+
+        let interrupted = InterruptedWorkload::read(&path).unwrap();
+        assert_eq!(interrupted.workload, workload);
+        assert_eq!(interrupted.steps, vec![step.clone()]);
+
+        // carried on, the entry cut short is gone and new ones are readable
+        let mut journal = Journal::resume(&path, &interrupted.workload, &interrupted.steps).unwrap();
+        journal.append(&step).unwrap();
+
+        let resumed = InterruptedWorkload::read(&path).unwrap();
+        assert_eq!(resumed.steps, vec![step.clone(), step]);
+    }
+}
@@ -1,13 +1,19 @@
 
 pub mod bytebuffer;
+pub mod changelog;
 pub mod dependency;
 pub mod i18n;
+pub mod journal;
 pub mod operation;
 pub mod package;
 pub mod product;
 pub mod repository;
+pub mod requirement;
+pub mod resolver;
 pub mod script;
 pub mod summary;
+pub mod transaction;
+pub mod verification;
 pub mod error;
 
 pub mod app;
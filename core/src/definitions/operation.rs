@@ -52,6 +52,7 @@ impl<'a> Operation<'a> {
         self.performer.finalize(app)?;
         log::info!("Operation '{:?}' completed.", self.get_kind());
 
+        let record = self.as_record()?;
         app.modify_summary(|summary| {
             let history = match target {
                 None => {
@@ -62,10 +63,11 @@ impl<'a> Operation<'a> {
                 }
             };
 
-            history.add_from_operation(self).map(|sum| ())
-        })?;
+            history.add(record.clone());
+        });
 
-        Ok(())
+        // reverted along with the rest of the workload if it fails later on
+        app.add_to_transaction(target, record)
     }
 
     /// Attemtps to revert underlaying operation
@@ -186,6 +188,11 @@ impl OperationHistory {
     pub fn get_records(&self) -> &[OperationRecord] {
         &self.records
     }
+
+    /// Gets operation records present in the history for in place modification
+    pub(crate) fn get_records_mut(&mut self) -> &mut [OperationRecord] {
+        &mut self.records
+    }
 }
 
 /// Represents an entry of 'Operation' in 'OperationHistory'
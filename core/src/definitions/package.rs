@@ -2,7 +2,7 @@ use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 
-use crate::helpers::serializer::{self, SerializationError};
+use crate::helpers::{checksum::{Checksum, ChecksumAlgorithm}, platform::Platform, serializer::{self, SerializationError}};
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
@@ -14,8 +14,40 @@ pub struct Package {
     pub default: bool,
     pub archive: String,
     pub size: u64,
+    /// Size of the extracted files
+    #[serde(default)]
+    pub installed_size: u64,
+    /// Digest of older repositories, superseded by 'checksum'
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub sha1: String,
-    pub script: String
+    #[serde(default)]
+    pub checksum: Option<Checksum>,
+    pub script: String,
+    /// Directory the package is extracted to, such as '@{App.TargetDirectory}/plugins/foo'. Relative paths are
+    /// resolved against the target directory. Empty for the target directory itself
+    #[serde(default)]
+    pub install_path: String,
+    /// File name of the changelog published next to the archive. Empty if there is none
+    #[serde(default)]
+    pub release_notes: String,
+    /// Globs of the configuration files, relative to the package directory. Changes the user made to them survive updates
+    #[serde(default)]
+    pub config_files: Vec<String>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+    #[serde(default)]
+    pub replaces: Vec<String>,
+    #[serde(default)]
+    pub os: String,
+    #[serde(default)]
+    pub arch: String,
+    #[serde(default)]
+    pub min_os_version: String,
+    /// Can only be removed along with the whole product
+    #[serde(default)]
+    pub required: bool
 }
 
 impl Package {
@@ -23,7 +55,7 @@ impl Package {
         Ok(serializer::from_json_file(path)?)
     }
 
-    pub fn from_definition(definition: &PackageDefinition, archive: &str, size: u64, sha1: &str, script: &str) -> Package {
+    pub fn from_definition(definition: &PackageDefinition, archive: &str, size: u64, checksum: &Checksum, script: &str) -> Package {
         Package {
             name: definition.name.clone(),
             display_name: definition.display_name.clone(),
@@ -31,11 +63,42 @@ impl Package {
             release_date: definition.release_date.clone(),
             default: definition.default,
             archive: archive.to_owned(),
-            sha1: sha1.to_owned(),
+            sha1: String::new(),
+            checksum: Some(checksum.clone()),
             script: script.to_owned(),
+            install_path: definition.install_path.clone(),
+            release_notes: String::new(),
+            config_files: definition.config_files.clone(),
+            dependencies: definition.dependencies.clone(),
+            conflicts: definition.conflicts.clone(),
+            replaces: definition.replaces.clone(),
+            os: definition.os.clone(),
+            arch: definition.arch.clone(),
+            min_os_version: definition.min_os_version.clone(),
+            required: definition.required,
+            installed_size: 0,
             size
         }
     }
+
+    /// Checks whether the package can be installed on 'platform'
+    pub fn is_supported_on(&self, platform: &Platform) -> bool {
+        platform.supports(&self.os, &self.arch, &self.min_os_version)
+    }
+
+    /// Gets how narrowly the build is targeted, builds for a single os and architecture rank the highest
+    pub fn get_specificity(&self) -> usize {
+        [&self.os, &self.arch, &self.min_os_version].iter().filter(|field| !field.is_empty()).count()
+    }
+
+    /// Gets the checksum package archive is verified against, falling back to SHA-1 of older repositories
+    pub fn get_checksum(&self) -> Option<Checksum> {
+        match &self.checksum {
+            Some(checksum) => Some(checksum.clone()),
+            None if !self.sha1.is_empty() => Some(Checksum::new(ChecksumAlgorithm::Sha1, &self.sha1)),
+            None => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
@@ -46,7 +109,47 @@ pub struct PackageDefinition {
     pub version: String,
     pub release_date: String,
     pub default: bool,
-    pub script: String
+    pub script: String,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+    #[serde(default)]
+    pub replaces: Vec<String>,
+
+    /// Directory template the package is extracted to, expanded when the package is installed. Empty for the target directory
+    #[serde(default)]
+    pub install_path: String,
+
+    /// Notes of this version, used when the meta folder has no 'CHANGELOG.md'
+    #[serde(default)]
+    pub release_notes: String,
+
+    /// Globs of the configuration files, such as 'conf/*.ini'. Files the user changed are kept on update,
+    /// the new version is written next to them as '<file>.new'
+    #[serde(default)]
+    pub config_files: Vec<String>,
+
+    /// Release channel the package is published to. Empty for the default channel
+    #[serde(default)]
+    pub channel: String,
+
+    /// Operating system the package is built for, such as 'windows'. Empty for any.
+    /// Overridden by 'data-<os>-<arch>' source folders
+    #[serde(default)]
+    pub os: String,
+
+    /// Architecture the package is built for, such as 'x86_64'. Empty for any
+    #[serde(default)]
+    pub arch: String,
+
+    /// Minimum operating system version the package runs on. Empty for any
+    #[serde(default)]
+    pub min_os_version: String,
+
+    /// Required packages, such as the core runtime, can only be removed along with the whole product
+    #[serde(default)]
+    pub required: bool
 }
 
 impl PackageDefinition {
@@ -54,7 +157,7 @@ impl PackageDefinition {
         Ok(serializer::from_json_file(path)?)
     }
 
-    pub fn define(&self, archive: &str, size: u64, sha1: &str, script: &str) -> Package {
-        Package::from_definition(self, archive, size, sha1, script)
+    pub fn define(&self, archive: &str, size: u64, checksum: &Checksum, script: &str) -> Package {
+        Package::from_definition(self, archive, size, checksum, script)
     }
 }
\ No newline at end of file
@@ -4,9 +4,9 @@ use std::path::Path;
 use directories::UserDirs;
 use serde::{Deserialize, Serialize};
 
-use crate::{helpers::{self, formatter::TemplateFormat, serializer::{self, SerializationError}, workflow::{self, Workflow}}, http::client};
+use crate::{helpers::{self, formatter::TemplateFormat, serializer::{self, SerializationError}, signature::{self, SignatureError}, workflow::{self, Workflow}, xdg}, http::{client::{self, HttpStreamError}, mirror::MirrorList}};
 
-use super::{error::{RepositoryFetchError, ScriptError}, package::Package, repository::Repository};
+use super::{error::{RepositoryFetchError, ScriptError}, package::Package, repository::{Repository, RepositoryHeader}};
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
@@ -18,6 +18,28 @@ pub struct Product {
     pub repository: String,
     pub script: String,
     pub target_directory: String,
+
+    /// Release channel to install from. Empty for the default channel
+    #[serde(default)]
+    pub channel: String,
+
+    /// Alternative base urls serving the same content as 'Repository'
+    #[serde(default)]
+    pub mirrors: Vec<Mirror>,
+
+    /// Base64 encoded Ed25519 key the repository metadata is signed with. Embedded by binarygen
+    #[serde(default)]
+    pub public_key: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Mirror {
+    pub url: String,
+
+    /// Mirrors with higher weight are tried first
+    #[serde(default)]
+    pub weight: u32,
 }
 
 impl Product{
@@ -29,7 +51,10 @@ impl Product{
             product_url: product_url.to_owned(),
             repository: repository.to_owned(),
             script: script.to_owned(),
-            target_directory: target_directory.to_owned()
+            target_directory: target_directory.to_owned(),
+            channel: String::new(),
+            mirrors: Vec::new(),
+            public_key: String::new()
         }
     }
 
@@ -47,10 +72,12 @@ impl Product{
         Self::from_template(template)
     }
 
+    /// Expands the placeholders of 'template'. Unknown or malformed placeholders are reported rather than kept,
+    /// '@{Key|}' is used for ones that may be left empty
     pub fn from_template(template: Product) -> Result<Product, SerializationError> {
         let formatter = template.create_formatter();
         let back_step = serializer::to_json(&template)?;
-        let json = formatter.format(&back_step);
+        let json = formatter.format_strict(&back_step)?;
         Ok(serializer::from_json(&json)?)
     }
 
@@ -59,7 +86,7 @@ impl Product{
         let user_directory = directories.home_dir();
 
         // use transformer to ensure its valid to be stored as json
-        TemplateFormat::new(Some(Box::new(|value| 
+        let formatter = TemplateFormat::new(Some(Box::new(|value| 
                 serializer::to_json(&value) // serialize to make sure it can be stored in json file, without breaking its format
                     .and_then(|transformed| Ok(transformed[1..transformed.len()-1].to_owned())) // remove quotes
                     .unwrap_or(value.to_string())) // or get value itself
@@ -71,24 +98,52 @@ impl Product{
             .add_replacement("App.ProductUrl", &self.product_url)
             .add_replacement("App.TargetDirectory", &self.target_directory)
             .add_replacement("App.Repository", &self.repository)
+            .add_replacement("App.Channel", &self.channel)
             .add_replacement("Directories.User.Home", user_directory.to_str().unwrap())
             .add_replacement("Directories.User.Documents", directories.document_dir().unwrap_or(user_directory).to_str().unwrap())
             .add_replacement("Directories.User.Downloads", directories.download_dir().unwrap_or(user_directory).to_str().unwrap())
-            .add_replacement("Directories.User.Desktop", directories.desktop_dir().unwrap_or(user_directory).to_str().unwrap())
+            .add_replacement("Directories.User.Desktop", directories.desktop_dir().unwrap_or(user_directory).to_str().unwrap());
+
+        let user = xdg::user_directories().into_iter().map(|(name, path)| (format!("Directories.User.{}", name), path));
+        let system = xdg::system_directories().into_iter().map(|(name, path)| (format!("Directories.System.{}", name), path));
+        user.chain(system).fold(formatter, |formatter, (key, path)| formatter.add_replacement(&key, &path.to_string_lossy()))
+    }
+
+    /// Gets base urls of the repository in the order they are tried. 'Repository' comes first,
+    /// mirrors follow by descending weight, keeping the listed order for equal weights
+    pub fn get_mirror_urls(&self) -> Vec<String> {
+        let mut mirrors = self.mirrors.iter().collect::<Vec<_>>();
+        mirrors.sort_by_key(|m| std::cmp::Reverse(m.weight));
+
+        std::iter::once(self.repository.clone())
+            .chain(mirrors.into_iter().map(|m| m.url.clone()))
+            .collect()
     }
 
-    pub fn get_path_to_package(&self, _package: &Package) -> std::path::PathBuf {
-        self.get_relative_target_directory()
+    /// Gets the directory 'package' is extracted to. 'InstallPath' of the package is expanded through the product's
+    /// formatter and resolved against the target directory, packages without one are extracted to the target directory itself
+    pub fn get_path_to_package(&self, package: &Package) -> std::path::PathBuf {
+        let target_directory = self.get_relative_target_directory();
+        if package.install_path.is_empty() {
+            return target_directory;
+        }
+
+        // installation folder might have been moved, resolve against where it is now
+        let formatter = self.create_formatter()
+            .add_replacement("App.TargetDirectory", target_directory.to_str().unwrap());
+
+        // expanded as a json string, the way product and repository templates are
+        let install_path = serializer::to_json(&package.install_path)
+            .and_then(|json| serializer::from_json::<String>(&formatter.format(&json)))
+            .unwrap_or(package.install_path.clone());
+
+        target_directory.join(install_path)
     }
 
     pub fn get_uri_to_package(&self, package: &Package) -> String {
         format!("{}packages/{}", self.repository, package.archive)
     }
 
-    pub fn get_uri_to_package_sha1(&self, package: &Package) -> String {
-        format!("{}packages/{}.sha1", self.repository, package.archive)
-    }
-
     pub fn get_uri_to_package_script(&self, package: &Package) -> Result<Option<String>, ScriptError> {
         if package.script.is_empty() {
             return Ok(None)
@@ -97,6 +152,22 @@ impl Product{
         Ok(Some(format!("{}packages/{}", self.repository, package.script)))
     }
 
+    pub fn get_uri_to_package_release_notes(&self, package: &Package) -> Option<String> {
+        if package.release_notes.is_empty() {
+            return None
+        }
+
+        Some(format!("{}packages/{}", self.repository, package.release_notes))
+    }
+
+    pub fn get_uri_to_installer(&self, header: &RepositoryHeader) -> Option<String> {
+        if header.installer.is_empty() {
+            return None
+        }
+
+        Some(format!("{}{}", self.repository, header.installer))
+    }
+
     pub fn get_uri_to_global_script(&self, repository: &Repository) -> Option<String> {
         // product struct also contains script field but if for some unknown reason
         // script file name at cloud gets changed it can cause issue as product struct is embeded.
@@ -132,10 +203,12 @@ impl Product{
         std::path::Path::new(&self.target_directory).to_path_buf()
     }
 
-    pub(super) async fn fetch_repository(&self) -> Result<Repository, RepositoryFetchError> {
-        let meta_uri = format!("{}repository.json", &self.repository);
-        let mut meta_str = client::get_text(&meta_uri, |_| ()).await?;
-        meta_str = self.create_formatter().format(&meta_str);
+    pub(super) async fn fetch_repository(&self, mirrors: &MirrorList) -> Result<Repository, RepositoryFetchError> {
+        let meta_str = self.fetch_repository_meta(mirrors).await?;
+
+        // refuse metadata this build can't understand before the rest of it is read
+        let header: RepositoryHeader = serializer::from_json(&meta_str)?;
+        header.check_compatibility()?;
 
         let repository: Repository = serializer::from_json(&meta_str)?;
 
@@ -143,6 +216,36 @@ impl Product{
         Ok(repository)
     }
 
+    /// Fetches only the header of the repository, which older installers are still able to read
+    pub(super) async fn fetch_repository_header(&self, mirrors: &MirrorList) -> Result<RepositoryHeader, RepositoryFetchError> {
+        let meta_str = self.fetch_repository_meta(mirrors).await?;
+        Ok(serializer::from_json(&meta_str)?)
+    }
+
+    /// Fetches 'repository.json', verifying its signature and expanding templates
+    async fn fetch_repository_meta(&self, mirrors: &MirrorList) -> Result<String, RepositoryFetchError> {
+        let meta_uri = format!("{}repository.json", &self.repository);
+        let meta_str = client::get_text_mirrored(mirrors, &meta_uri, |_| ()).await?;
+
+        // an installer built without a key can't tell a genuine repository from a tampered one
+        if self.public_key.is_empty() {
+            log::error!("Product has no public key embedded, the repository can't be verified.");
+            return Err(SignatureError::MissingKey.into());
+        }
+
+        let signature = match client::get_text_mirrored(mirrors, &format!("{}.sig", meta_uri), |_| ()).await {
+            Ok(signature) => signature,
+            Err(HttpStreamError::StatusCode(404)) => return Err(SignatureError::Unsigned.into()),
+            Err(err) => return Err(err.into()),
+        };
+
+        // signature covers the metadata as published, before templates are expanded
+        signature::verify(&self.public_key, meta_str.as_bytes(), &signature)?;
+        log::info!("Verified signature of the repository.");
+
+        Ok(self.create_formatter().format(&meta_str))
+    }
+
     pub(super) fn dump(&self) -> Result<(), SerializationError> {
         let mut file = helpers::file::open_create(&self.get_path_to_self_struct_target())?;
         helpers::file::write_all_file(&mut file, serializer::to_json(self)?.as_bytes())?;
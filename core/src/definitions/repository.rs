@@ -1,36 +1,305 @@
 
+use std::cmp::Ordering;
+
 use serde::{Deserialize, Serialize};
 
-use super::package::Package;
+use crate::helpers::{checksum::Checksum, platform::Platform, versioning::version_compare};
+
+use super::{error::RepositoryFetchError, package::Package, summary::PackageInstallation};
+
+/// Layout version of 'repository.json' written by this build. Repositories with a newer one are refused
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Version of the running installer, checked against 'MinimumInstallerVersion' of repositories
+pub const INSTALLER_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct Repository {
+    /// Layout version of the metadata. 0 for repositories published before it was introduced
+    #[serde(default)]
+    pub schema_version: u32,
+
+    /// Oldest installer version able to install from the repository. Empty if any version will do
+    #[serde(default)]
+    pub minimum_installer_version: String,
+
+    /// File name of the installer binary published next to the metadata, outdated maintenance tools replace themselves with it
+    #[serde(default)]
+    pub installer: String,
+
+    #[serde(default)]
+    pub installer_checksum: Option<Checksum>,
+
     pub application_name: String,
     pub script: String,
     pub packages: Vec<Package>,
-    pub size: u64
+    pub size: u64,
+
+    /// Release channels other than the default one, such as 'beta'
+    #[serde(default)]
+    pub channels: Vec<Channel>,
+
+    /// Named sets of packages selectable as a single unit, such as 'Full' or 'Minimal'
+    #[serde(default)]
+    pub groups: Vec<PackageGroup>,
+}
+
+/// Fields of 'repository.json' that are read ahead of the rest, so metadata this build
+/// does not understand is refused instead of being misread
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct RepositoryHeader {
+    #[serde(default)]
+    pub schema_version: u32,
+    #[serde(default)]
+    pub minimum_installer_version: String,
+    #[serde(default)]
+    pub installer: String,
+    #[serde(default)]
+    pub installer_checksum: Option<Checksum>,
+}
+
+impl RepositoryHeader {
+    /// Checks whether the running installer is able to install from the repository
+    pub fn check_compatibility(&self) -> Result<(), RepositoryFetchError> {
+        if self.schema_version > SCHEMA_VERSION {
+            return Err(RepositoryFetchError::UnsupportedSchema(self.schema_version));
+        }
+
+        if !self.minimum_installer_version.is_empty() && version_compare(INSTALLER_VERSION, &self.minimum_installer_version)? == Ordering::Less {
+            return Err(RepositoryFetchError::OutdatedInstaller(self.minimum_installer_version.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Packages published to a release channel
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Channel {
+    pub name: String,
+    pub packages: Vec<Package>,
+}
+
+/// A named set of packages, selected by its name wherever a package name is accepted
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct PackageGroup {
+    pub name: String,
+    pub display_name: String,
+    pub packages: Vec<String>,
 }
 
 impl Repository {
     pub fn new(application_name: &str, size: u64) -> Self {
         Self {
+            schema_version: SCHEMA_VERSION,
+            minimum_installer_version: String::new(),
+            installer: String::new(),
+            installer_checksum: None,
             application_name: application_name.to_string(),
             script: String::new(),
             packages: Vec::new(),
-            size
+            size,
+            channels: Vec::new(),
+            groups: Vec::new(),
         }
     }
 
+    /// Gets the repository as seen from 'channel' on the running platform. An empty name refers to the default channel.
+    /// Packages listed under the channel take precedence over default ones with the same name. Returns 'None' if the channel is not listed
+    pub fn get_channel(&self, channel: &str) -> Option<Repository> {
+        self.get_channel_on(channel, Platform::current())
+    }
+
+    fn get_channel_on(&self, channel: &str, platform: &Platform) -> Option<Repository> {
+        let mut packages = builds_for(&self.packages, platform);
+
+        if !channel.is_empty() {
+            let listed = self.channels.iter().find(|c| c.name == channel)?;
+            let listed = builds_for(&listed.packages, platform);
+
+            packages.retain(|p| !listed.iter().any(|l| l.name == p.name));
+            packages.extend(listed);
+        }
+
+        Some(Repository { packages, channels: Vec::new(), ..self.clone() })
+    }
+
+    /// Gets names of the release channels, excluding the default one
+    pub fn get_channel_names(&self) -> Vec<String> {
+        self.channels.iter().map(|c| c.name.clone()).collect()
+    }
+
+    /// Gets the most specific build of the package that runs on the running platform
     pub fn get_package(&self, package_name: &str) -> Option<Package> {
-        self.packages.iter().find(|e| e.name == package_name)
-            .map(|f| f.clone())
+        build_for(&self.packages, package_name, Platform::current()).cloned()
+    }
+
+    /// Gets the package group named 'group_name'
+    pub fn get_group(&self, group_name: &str) -> Option<&PackageGroup> {
+        self.groups.iter().find(|g| g.name == group_name)
     }
 
+    /// Expands group names in 'names' to the names of their packages, keeping package names as they are
+    pub fn expand_names(&self, names: &[String]) -> Vec<String> {
+        let mut expanded = Vec::<String>::new();
+        for name in names {
+            match self.get_group(name) {
+                Some(group) => expanded.extend(group.packages.iter().cloned()),
+                None => expanded.push(name.clone()),
+            }
+        }
+
+        expanded
+    }
+
+    /// Gets the packages 'names' refer to, built for the running platform, along with the names of the selected groups.
+    /// Names that match neither a package nor a group are skipped
+    pub fn select(&self, names: &[String]) -> (Vec<Package>, Vec<String>) {
+        let groups = names.iter()
+            .filter(|name| self.get_group(name).is_some())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut packages = Vec::<Package>::new();
+        for name in self.expand_names(names) {
+            match self.get_package(&name) {
+                Some(package) if !packages.iter().any(|p| p.name == package.name) => packages.push(package),
+                Some(_) => { }
+                None => log::warn!("Package '{}' not found in repository, skipping.", name),
+            }
+        }
+
+        (packages, groups)
+    }
+
+    /// Gets packages of 'groups' that are not 'installed', such as the ones that joined a group after it was installed
+    pub fn get_joined_packages(&self, groups: &[String], installed: &[PackageInstallation]) -> Vec<Package> {
+        let mut joined = Vec::<Package>::new();
+        for name in groups.iter().filter_map(|group| self.get_group(group)).flat_map(|group| group.packages.iter()) {
+            if installed.iter().any(|p| &p.name == name) || joined.iter().any(|p| &p.name == name) {
+                continue;
+            }
+
+            if let Some(package) = self.get_package(name) {
+                joined.push(package);
+            }
+        }
+
+        joined
+    }
+
+    /// Gets default packages built for the running platform
     pub fn get_default_packages(&self) -> Vec<Package> {
-        self.packages.iter()
+        builds_for(&self.packages, Platform::current()).into_iter()
             .filter(|e| e.default)
-            .cloned()
             .collect()
     }
+}
+
+/// Gets the most specific build of package 'name' among 'packages' that runs on 'platform'. A package might ship a generic build
+/// along with builds for certain platforms, the first one listed wins among equally specific builds
+fn build_for<'a>(packages: &'a [Package], name: &str, platform: &Platform) -> Option<&'a Package> {
+    packages.iter().rev()
+        .filter(|p| p.name == name && p.is_supported_on(platform))
+        .max_by_key(|p| p.get_specificity())
+}
+
+/// Gets a single build per package among 'packages' that runs on 'platform', in the order packages are listed
+fn builds_for(packages: &[Package], platform: &Platform) -> Vec<Package> {
+    let mut builds = Vec::<Package>::new();
+    for package in packages {
+        if builds.iter().any(|b| b.name == package.name) {
+            continue;
+        }
+
+        if let Some(build) = build_for(packages, &package.name, platform) {
+            builds.push(build.clone());
+        }
+    }
+
+    builds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, os: &str, arch: &str) -> Package {
+        Package { name: name.to_owned(), os: os.to_owned(), arch: arch.to_owned(), default: true, ..Default::default() }
+    }
+
+    #[test]
+    fn test_builds_for_most_specific() {
+        let linux = Platform::new("linux", "x86_64", None);
+        let windows = Platform::new("windows", "x86_64", None);
+        let mut repository = Repository::new("app", 0);
+        repository.packages = vec![package("data", "", ""), package("data", "linux", "x86_64"), package("tools", "", "")];
+
+        let packages = repository.get_channel_on("", &linux).unwrap().packages;
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0], package("data", "linux", "x86_64"));
+        assert_eq!(packages[1], package("tools", "", ""));
+
+        assert_eq!(build_for(&repository.packages, "data", &windows), Some(&package("data", "", "")));
+    }
+
+    fn group(name: &str, packages: &[&str]) -> PackageGroup {
+        PackageGroup { name: name.to_owned(), display_name: name.to_owned(), packages: packages.iter().map(|p| p.to_string()).collect() }
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_expand_names() {
+        let mut repository = Repository::new("app", 0);
+        repository.groups = vec![group("office", &["writer", "sheets"])];
+
+        assert_eq!(repository.expand_names(&names(&["office", "tools"])), names(&["writer", "sheets", "tools"]));
+        assert_eq!(repository.expand_names(&names(&["tools"])), names(&["tools"]));
+    }
+
+    #[test]
+    fn test_select() {
+        let mut repository = Repository::new("app", 0);
+        repository.packages = vec![package("writer", "", ""), package("sheets", "", ""), package("tools", "", "")];
+        repository.groups = vec![group("office", &["writer", "sheets"])];
+
+        // packages listed both directly and through a group are selected once, unknown names are skipped
+        let (packages, groups) = repository.select(&names(&["writer", "office", "unknown"]));
+        assert_eq!(packages.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), ["writer", "sheets"]);
+        assert_eq!(groups, names(&["office"]));
+    }
+
+    #[test]
+    fn test_joined_packages() {
+        let mut repository = Repository::new("app", 0);
+        repository.packages = vec![package("writer", "", ""), package("sheets", "", ""), package("slides", "", "")];
+        repository.groups = vec![group("office", &["writer", "sheets", "slides"]), group("extras", &["slides"])];
+
+        let installed = vec![
+            PackageInstallation { name: "writer".to_owned(), ..Default::default() },
+            PackageInstallation { name: "sheets".to_owned(), ..Default::default() },
+        ];
+
+        let joined = repository.get_joined_packages(&names(&["office", "extras"]), &installed);
+        assert_eq!(joined, vec![package("slides", "", "")]);
+        assert!(repository.get_joined_packages(&[], &installed).is_empty());
+    }
+
+    #[test]
+    fn test_channel_builds() {
+        let linux = Platform::new("linux", "x86_64", None);
+        let mut repository = Repository::new("app", 0);
+        repository.packages = vec![package("data", "", "")];
+        repository.channels = vec![Channel { name: "beta".to_owned(), packages: vec![package("data", "", ""), package("data", "linux", "")] }];
+
+        let packages = repository.get_channel_on("beta", &linux).unwrap().packages;
+        assert_eq!(packages, vec![package("data", "linux", "")]);
+    }
 }
\ No newline at end of file
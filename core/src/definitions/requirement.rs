@@ -0,0 +1,91 @@
+use std::{cmp::Ordering, fmt};
+
+use crate::helpers::versioning::{ConstraintOp, Version, VersionConstraint, VersionError};
+
+use super::error::DependencyError;
+
+/// A reference to another package with optional version bounds, written as 'core >= 2.1, < 3'.
+/// Used by the 'Dependencies', 'Conflicts' and 'Replaces' fields of package definitions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageRequirement {
+    pub name: String,
+    pub constraints: Vec<VersionConstraint>,
+}
+
+impl PackageRequirement {
+    pub fn parse(text: &str) -> Result<Self, DependencyError> {
+        let name = Self::name_of(text);
+        if name.is_empty() {
+            return Err(DependencyError::InvalidRequirement(text.to_owned()));
+        }
+
+        let rest = text.trim()[name.len()..].trim();
+        let mut constraints = vec![];
+
+        if !rest.is_empty() {
+            for part in rest.split(',') {
+                constraints.push(VersionConstraint::parse(part)?);
+            }
+        }
+
+        Ok(PackageRequirement { name: name.to_owned(), constraints })
+    }
+
+    /// Gets the package name part of a requirement without validating its bounds
+    pub fn name_of(text: &str) -> &str {
+        let text = text.trim();
+        let end = text.find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '=' | '!' | ','))
+            .unwrap_or(text.len());
+
+        &text[..end]
+    }
+
+    /// Checks whether 'version' satisfies every bound of this requirement
+    pub fn matches(&self, version: &str) -> Result<bool, VersionError> {
+        let version = Version::parse(version)?;
+        Ok(self.constraints.iter().all(|c| c.matches(&version)))
+    }
+
+    /// Checks whether a version of the same package can satisfy both this requirement and 'other'
+    pub fn overlaps(&self, other: &PackageRequirement) -> bool {
+        if self.name != other.name {
+            return false;
+        }
+
+        let constraints = self.constraints.iter().chain(other.constraints.iter()).collect::<Vec<_>>();
+
+        // an exact version either satisfies every bound or none does
+        if let Some(exact) = constraints.iter().find(|c| c.op == ConstraintOp::Eq) {
+            return constraints.iter().all(|c| c.matches(&exact.version));
+        }
+
+        // tightest bounds, exclusive ones are tighter than inclusive ones of the same version
+        let lower = constraints.iter()
+            .filter(|c| matches!(c.op, ConstraintOp::Gt | ConstraintOp::Ge))
+            .max_by(|a, b| a.version.cmp(&b.version).then((a.op == ConstraintOp::Gt).cmp(&(b.op == ConstraintOp::Gt))));
+        let upper = constraints.iter()
+            .filter(|c| matches!(c.op, ConstraintOp::Lt | ConstraintOp::Le))
+            .min_by(|a, b| a.version.cmp(&b.version).then((b.op == ConstraintOp::Lt).cmp(&(a.op == ConstraintOp::Lt))));
+
+        match (lower, upper) {
+            (Some(lower), Some(upper)) => match lower.version.cmp(&upper.version) {
+                Ordering::Less => true,
+                Ordering::Equal => lower.op == ConstraintOp::Ge && upper.op == ConstraintOp::Le
+                    && !constraints.iter().any(|c| c.op == ConstraintOp::Ne && c.version == lower.version),
+                Ordering::Greater => false,
+            },
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for PackageRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.constraints.is_empty() {
+            write!(f, " {}", self.constraints.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", "))?;
+        }
+
+        Ok(())
+    }
+}
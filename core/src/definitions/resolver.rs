@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use super::{error::DependencyError, package::Package, repository::Repository, requirement::PackageRequirement, summary::PackageInstallation};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mark {
+    Visiting,
+    Done,
+}
+
+/// Outcome of resolving a set of packages against the repository and the current installation
+#[derive(Clone, Debug, Default)]
+pub struct Resolution {
+    /// Packages to install or update, ordered so that every package comes after its dependencies.
+    /// Packages that are already installed with the same version are included as well.
+    pub order: Vec<Package>,
+
+    /// Installed packages that are superseded by a package in 'order' through its 'Replaces' field
+    pub replaced: Vec<PackageInstallation>,
+}
+
+struct RemoteResolver<'a> {
+    repository: &'a Repository,
+    installed: &'a [PackageInstallation],
+    targets: &'a [Package],
+    marks: HashMap<String, Mark>,
+    path: Vec<String>,
+    order: Vec<Package>,
+}
+
+/// Resolves 'targets' along with their transitive dependencies from the 'repository'.
+/// Version bounds of dependencies are checked against the 'installed' packages and remote versions,
+/// conflicts and replacements are checked against the final set. Nothing is downloaded.
+pub fn install_order(repository: &Repository, installed: &[PackageInstallation], targets: &[Package]) -> Result<Resolution, DependencyError> {
+    let mut resolver = RemoteResolver {
+        repository,
+        installed,
+        targets,
+        marks: HashMap::new(),
+        path: Vec::new(),
+        order: Vec::new(),
+    };
+
+    for target in targets {
+        resolver.visit(target)?;
+    }
+
+    let order = resolver.order;
+    let mut replaced = Vec::<PackageInstallation>::new();
+
+    for package in &order {
+        for text in &package.replaces {
+            let requirement = PackageRequirement::parse(text)?;
+            for local in installed.iter().filter(|local| local.name == requirement.name && local.name != package.name) {
+                if requirement.matches(&local.version)? && !replaced.iter().any(|r| r.name == local.name) {
+                    log::info!("Installed package '{}' {} is replaced by '{}' {}.", local.name, local.version, package.name, package.version);
+                    replaced.push(local.clone());
+                }
+            }
+        }
+    }
+
+    // every package that stays installed, with the version it will have after the workload
+    let mut outcome = installed.iter()
+        .filter(|local| !replaced.iter().any(|r| r.name == local.name))
+        .filter(|local| !order.iter().any(|p| p.name == local.name))
+        .map(|local| (local.name.clone(), local.version.clone(), local.conflicts.clone()))
+        .collect::<Vec<_>>();
+
+    outcome.extend(order.iter().map(|p| (p.name.clone(), p.version.clone(), p.conflicts.clone())));
+
+    for (name, _, conflicts) in &outcome {
+        for text in conflicts {
+            let requirement = PackageRequirement::parse(text)?;
+            for (other, version, _) in outcome.iter().filter(|(other, _, _)| other != name && *other == requirement.name) {
+                if requirement.matches(version)? {
+                    log::error!("Package '{}' conflicts with '{}' ({}).", name, other, requirement);
+                    return Err(DependencyError::Conflict(name.clone(), other.clone()));
+                }
+            }
+        }
+    }
+
+    Ok(Resolution { order, replaced })
+}
+
+impl RemoteResolver<'_> {
+    fn visit(&mut self, package: &Package) -> Result<(), DependencyError> {
+        match self.marks.get(&package.name) {
+            Some(Mark::Done) => return Ok(()),
+            Some(Mark::Visiting) => return Err(cycle(&self.path, &package.name)),
+            None => {}
+        }
+
+        self.marks.insert(package.name.clone(), Mark::Visiting);
+        self.path.push(package.name.clone());
+
+        for text in &package.dependencies {
+            let requirement = PackageRequirement::parse(text)?;
+            if let Some(next) = self.provider(package, &requirement)? {
+                self.visit(&next)?;
+            }
+        }
+
+        self.path.pop();
+        self.marks.insert(package.name.clone(), Mark::Done);
+        self.order.push(package.clone());
+        Ok(())
+    }
+
+    /// Finds the package satisfying 'requirement' of 'package'. Returns 'None' when an installed package already satisfies it
+    fn provider(&self, package: &Package, requirement: &PackageRequirement) -> Result<Option<Package>, DependencyError> {
+        let unsatisfied = |version: &str| DependencyError::Unsatisfied(package.name.clone(), requirement.to_string(), version.to_owned());
+
+        // explicitly requested packages take precedence over installed ones
+        if let Some(target) = self.targets.iter().find(|p| p.name == requirement.name) {
+            if !requirement.matches(&target.version)? {
+                return Err(unsatisfied(&target.version));
+            }
+
+            return Ok(Some(target.clone()));
+        }
+
+        let remote = match self.repository.get_package(&requirement.name) {
+            Some(remote) => Some(remote),
+            None => self.replacement(requirement)?,
+        };
+
+        if let Some(local) = self.installed.iter().find(|p| p.name == requirement.name) {
+            if requirement.matches(&local.version)? {
+                return Ok(None);
+            }
+
+            // installed version is out of bounds, an update is required
+            return match remote {
+                Some(remote) if requirement.matches(&remote.version)? => Ok(Some(remote)),
+                _ => Err(unsatisfied(&local.version)),
+            };
+        }
+
+        match remote {
+            // replacements are only picked when the versions they replace overlap with the requirement
+            Some(remote) if remote.name != requirement.name => Ok(Some(remote)),
+            Some(remote) if requirement.matches(&remote.version)? => Ok(Some(remote)),
+            Some(remote) => Err(unsatisfied(&remote.version)),
+            None => Err(DependencyError::Missing(package.name.clone(), requirement.to_string())),
+        }
+    }
+
+    /// Finds a package replacing the one 'requirement' refers to, through a 'Replaces' entry whose bounds overlap with the requirement's
+    fn replacement(&self, requirement: &PackageRequirement) -> Result<Option<Package>, DependencyError> {
+        for package in &self.repository.packages {
+            for text in &package.replaces {
+                if !PackageRequirement::parse(text)?.overlaps(requirement) {
+                    continue;
+                }
+
+                if let Some(replacement) = self.repository.get_package(&package.name) {
+                    return Ok(Some(replacement));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Resolves removal order of 'targets' among the 'installed' packages.
+/// Installed packages that depend on any of the targets are either added to the removal set when 'cascade' is set,
+/// or reported with 'DependencyError::RequiredBy'. Returned packages are ordered so that dependents come first.
+pub fn removal_order(installed: &[PackageInstallation], targets: &[PackageInstallation], cascade: bool) -> Result<Vec<PackageInstallation>, DependencyError> {
+    let mut removal = targets.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+    let requires = |p: &PackageInstallation, name: &str| p.dependencies.iter().any(|d| PackageRequirement::name_of(d) == name);
+
+    loop {
+        let dependents = installed.iter()
+            .filter(|p| !removal.contains(&p.name))
+            .filter(|p| removal.iter().any(|r| requires(p, r)))
+            .collect::<Vec<_>>();
+
+        if dependents.is_empty() {
+            break;
+        }
+
+        if !cascade {
+            let required = removal.iter().find(|r| requires(dependents[0], r)).unwrap().clone();
+            let required_by = dependents.iter()
+                .filter(|p| requires(p, &required))
+                .map(|p| p.name.clone())
+                .collect();
+
+            return Err(DependencyError::RequiredBy(required, required_by));
+        }
+
+        log::info!("Cascading removal to dependent package(s): {}", dependents.iter().map(|p| p.name.clone()).collect::<Vec<_>>().join(", "));
+        removal.extend(dependents.iter().map(|p| p.name.clone()));
+    }
+
+    let mut marks = HashMap::<String, Mark>::new();
+    let mut path = Vec::<String>::new();
+    let mut order = Vec::<PackageInstallation>::new();
+
+    for name in &removal {
+        visit_local(installed, name, &mut marks, &mut path, &mut order)?;
+    }
+
+    order.retain(|p| removal.contains(&p.name));
+    order.reverse();
+    Ok(order)
+}
+
+fn visit_local(installed: &[PackageInstallation], name: &str, marks: &mut HashMap<String, Mark>, path: &mut Vec<String>, order: &mut Vec<PackageInstallation>) -> Result<(), DependencyError> {
+    // dependencies that are not installed are not part of the removal graph
+    let Some(package) = installed.iter().find(|p| p.name == name) else {
+        return Ok(());
+    };
+
+    match marks.get(name) {
+        Some(Mark::Done) => return Ok(()),
+        Some(Mark::Visiting) => return Err(cycle(path, name)),
+        None => {}
+    }
+
+    marks.insert(name.to_owned(), Mark::Visiting);
+    path.push(name.to_owned());
+
+    for dependency in &package.dependencies {
+        visit_local(installed, PackageRequirement::name_of(dependency), marks, path, order)?;
+    }
+
+    path.pop();
+    marks.insert(name.to_owned(), Mark::Done);
+    order.push(package.clone());
+    Ok(())
+}
+
+fn cycle(path: &[String], name: &str) -> DependencyError {
+    let start = path.iter().position(|p| p == name).unwrap_or(0);
+    let mut members = path[start..].to_vec();
+    members.push(name.to_owned());
+
+    log::error!("Dependency cycle detected: {}", members.join(" -> "));
+    DependencyError::Cycle(members)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::definitions::{package::Package, repository::Repository, summary::PackageInstallation};
+
+    use super::*;
+
+    fn package(name: &str, version: &str, dependencies: &[&str]) -> Package {
+        Package { name: name.to_owned(), version: version.to_owned(), dependencies: dependencies.iter().map(|d| d.to_string()).collect(), ..Default::default() }
+    }
+
+    fn installation(name: &str, version: &str, dependencies: &[&str]) -> PackageInstallation {
+        PackageInstallation { name: name.to_owned(), version: version.to_owned(), dependencies: dependencies.iter().map(|d| d.to_string()).collect(), ..Default::default() }
+    }
+
+    #[test]
+    fn test_install_order() {
+        let mut repository = Repository::new("test", 0);
+        repository.packages = vec![package("plugin", "1.0", &["core", "ui"]), package("ui", "1.0", &["core"]), package("core", "1.0", &[])];
+
+        let resolution = install_order(&repository, &[], &[repository.get_package("plugin").unwrap()]).unwrap();
+        let names = resolution.order.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["core", "ui", "plugin"]);
+    }
+
+    #[test]
+    fn test_install_order_cycle() {
+        let mut repository = Repository::new("test", 0);
+        repository.packages = vec![package("a", "1.0", &["b"]), package("b", "1.0", &["c"]), package("c", "1.0", &["a"])];
+
+        match install_order(&repository, &[], &[repository.get_package("a").unwrap()]) {
+            Err(DependencyError::Cycle(members)) => assert_eq!(members, vec!["a", "b", "c", "a"]),
+            other => panic!("expected cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_install_order_constraints() {
+        let mut repository = Repository::new("test", 0);
+        repository.packages = vec![package("plugin", "1.0", &["core >= 2.1, < 3"]), package("core", "2.4", &[])];
+        let plugin = repository.get_package("plugin").unwrap();
+
+        // installed core is too old, remote one satisfies the bounds
+        let installed = vec![installation("core", "2.0", &[])];
+        let resolution = install_order(&repository, &installed, &[plugin.clone()]).unwrap();
+        assert_eq!(resolution.order.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), vec!["core", "plugin"]);
+
+        repository.packages[1].version = "3.0".to_owned();
+        assert!(matches!(install_order(&repository, &installed, &[plugin]), Err(DependencyError::Unsatisfied(_, _, _))));
+    }
+
+    #[test]
+    fn test_install_order_conflicts_and_replaces() {
+        let mut repository = Repository::new("test", 0);
+        let mut renamed = package("new-core", "1.0", &[]);
+        renamed.replaces = vec!["old-core".to_owned()];
+        let mut rival = package("rival", "1.0", &[]);
+        rival.conflicts = vec!["new-core < 2".to_owned()];
+        repository.packages = vec![renamed.clone(), rival.clone()];
+
+        let installed = vec![installation("old-core", "0.9", &[])];
+        let resolution = install_order(&repository, &installed, &[renamed.clone()]).unwrap();
+        assert_eq!(resolution.replaced.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), vec!["old-core"]);
+
+        assert!(matches!(install_order(&repository, &installed, &[renamed, rival]), Err(DependencyError::Conflict(_, _))));
+    }
+
+    #[test]
+    fn test_install_order_replacement_bounds() {
+        let mut repository = Repository::new("test", 0);
+        let mut renamed = package("new-core", "1.0", &[]);
+        renamed.replaces = vec!["old-core < 2".to_owned()];
+        repository.packages = vec![package("plugin", "1.0", &["old-core >= 2"]), renamed];
+        let plugin = repository.get_package("plugin").unwrap();
+
+        // replaces versions the plugin can't work with
+        assert!(matches!(install_order(&repository, &[], &[plugin.clone()]), Err(DependencyError::Missing(_, _))));
+
+        repository.packages[1].replaces = vec!["old-core < 3".to_owned()];
+        let resolution = install_order(&repository, &[], &[plugin]).unwrap();
+        assert_eq!(resolution.order.iter().map(|p| p.name.as_str()).collect::<Vec<_>>(), vec!["new-core", "plugin"]);
+    }
+
+    #[test]
+    fn test_removal_order() {
+        let installed = vec![installation("core", "1.0", &[]), installation("ui", "1.0", &["core"]), installation("plugin", "1.0", &["ui >= 1"])];
+        let targets = vec![installed[0].clone()];
+
+        assert!(matches!(removal_order(&installed, &targets, false), Err(DependencyError::RequiredBy(_, _))));
+
+        let order = removal_order(&installed, &targets, true).unwrap();
+        let names = order.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["plugin", "ui", "core"]);
+    }
+}
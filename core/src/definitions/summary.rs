@@ -3,11 +3,21 @@ use std::{fmt, ops::{Deref, DerefMut}, path::{Path, PathBuf}};
 
 use serde::{Deserialize, Serialize};
 
-use crate::helpers::{self, serializer::{self, SerializationError}, versioning::version_compare};
+use crate::helpers::{self, formatter::TemplateFormat, serializer::{self, SerializationError}, versioning::{version_compare, VersionError}};
 
-use super::{error::PackageUninstallError, operation::OperationHistory, package::Package, product::Product};
+use super::{error::PackageUninstallError, operation::OperationHistory, package::Package, product::Product, requirement::PackageRequirement};
 
-#[derive(Clone, Debug)]
+/// Layout version of 'instally_summary.json' written by this build
+pub const SUMMARY_SCHEMA_VERSION: u32 = 1;
+
+/// Suffix of the previous generation of the summary, kept in case the current one can't be read
+const BACKUP_SUFFIX: &str = ".bak";
+
+/// Upgrades a summary of version 'index + 1' by one version. Summaries written before schema versioning was
+/// introduced have no version and are read as they are, every field added since falls back to its serde default
+const MIGRATIONS: [fn(&mut serde_json::Value); SUMMARY_SCHEMA_VERSION as usize - 1] = [];
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct InstallationSummary {
     path: std::path::PathBuf,
     inner: InstallitionSummaryInner
@@ -22,9 +32,23 @@ impl Default for InstallationSummary {
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct InstallitionSummaryInner {
+    #[serde(default)]
+    pub schema_version: u32,
     pub application_name: String,
     pub packages: Vec<PackageInstallation>,
     pub operations: OperationHistory,
+
+    /// Release channel the packages are installed from. 'None' for installations made before channels existed
+    #[serde(default)]
+    pub channel: Option<String>,
+
+    /// Package groups selected for installation. Packages joining these groups are installed by later updates
+    #[serde(default)]
+    pub groups: Vec<String>,
+
+    /// Files and directories uninstallations couldn't remove, such as directories holding files the user created
+    #[serde(default)]
+    pub leftovers: Vec<PathBuf>,
 }
 
 impl Deref for InstallationSummary {
@@ -48,18 +72,35 @@ impl InstallationSummary {
         InstallationSummary {
             path: path.into(),
             inner: InstallitionSummaryInner { 
+                schema_version: SUMMARY_SCHEMA_VERSION,
                 application_name: product.name.clone(),
                 packages: Vec::<PackageInstallation>::default(),
-                operations: OperationHistory::default()
+                operations: OperationHistory::default(),
+                channel: None,
+                groups: Vec::new(),
+                leftovers: Vec::new()
             }
         }
     }
 
-    /// Attempts to read installation summary from cwdir
+    /// Attempts to read installation summary from cwdir, falling back to its backup if it's missing or unreadable
     pub(crate) fn read() -> Result<Self, SerializationError> {
         let struct_path = Path::new("instally_summary.json");
-        let summary: InstallitionSummaryInner = serializer::from_json_file(&struct_path)?;
-        Ok(InstallationSummary { path: struct_path.to_path_buf(), inner: summary })
+        Ok(InstallationSummary { path: struct_path.to_path_buf(), inner: read_with_backup(struct_path, None)? })
+    }
+
+    /// Reads the persisted summary again, picking up changes another process made since. Does nothing if it was never persisted
+    pub(crate) fn reload(&mut self) -> Result<(), SerializationError> {
+        if self.path.exists() || backup_path(&self.path).exists() {
+            self.inner = read_with_backup(&self.path, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Gets the path the summary is persisted to
+    pub fn get_path(&self) -> &Path {
+        &self.path
     }
 
     /// Attempts to read installation summary at installation directory, creating new one if not present
@@ -67,31 +108,23 @@ impl InstallationSummary {
         Self::read_or_create(product, &std::path::PathBuf::from(&product.target_directory))
     }
 
-    /// Attempts to read installation summary at cwdir, creating new one if not present
+    /// Attempts to read installation summary at 'base', falling back to its backup. Creates new one if neither can be read
     pub(crate) fn read_or_create(product: &Product, base: &PathBuf) -> Result<Self, SerializationError> {
         let struct_path = base.join("instally_summary.json");
 
-        let mut file = match helpers::file::open(struct_path.clone()) {
-            Ok(f) => f,
-            Err(err) =>  {
-                log::info!("Failed to open installition summary file. Creating new one. {}", err);
-                helpers::file::create_dir_all(base)?; // ensure path is existing
-                helpers::file::open_create(struct_path.clone())?
-            }
-        };
-
-        let mut weak_struct = String::new();
-        helpers::file::read_to_string_from_file(&mut file, &mut weak_struct)?;
-        weak_struct = product.create_formatter().format(&weak_struct);
-
-        let inner: InstallitionSummaryInner = match serializer::from_json(&weak_struct) {
+        let inner = match read_with_backup(&struct_path, Some(&product.create_formatter())) {
             Ok(r) => r,
             Err(some) => {
-                log::info!("Failed to deserialize installition summary file. Using default. {:?}", some);
+                log::info!("Failed to read installition summary file. Using default. {:?}", some);
+                helpers::file::create_dir_all(base)?; // ensure path is existing
                 InstallitionSummaryInner { 
+                    schema_version: SUMMARY_SCHEMA_VERSION,
                     application_name: product.name.clone(),
                     packages: Vec::<PackageInstallation>::default(),
-                    operations: OperationHistory::default()
+                    operations: OperationHistory::default(),
+                    channel: None,
+                    groups: Vec::new(),
+                    leftovers: Vec::new()
                 }
             }
         }; 
@@ -120,33 +153,72 @@ impl InstallationSummary {
         None
     }
     
-    /// Checks available updates for specified 'packages'
-    pub fn cross_check(&self, packages: &[Package]) -> CrossCheckSummary {
+    /// Checks available updates for specified 'packages'. Remote versions older than the installed ones are reported as downgrades.
+    /// Installed packages that are no longer listed but superseded by one of the 'packages' through its 'Replaces' field are reported as replacements
+    pub fn cross_check(&self, packages: &[Package]) -> Result<CrossCheckSummary, VersionError> {
         let mut updates = vec![];
+        let mut downgrades = vec![];
         let mut map = vec![];
         let mut not_installed = vec![];
+        let mut replacements = vec![];
+
+        for local in self.packages.iter().filter(|local| !packages.iter().any(|remote| remote.name == local.name)) {
+            // malformed relations are reported by the resolver once the replacement is applied
+            let replacement = packages.iter().find(|remote| remote.replaces.iter()
+                .filter_map(|text| PackageRequirement::parse(text).ok())
+                .any(|requirement| requirement.name == local.name && requirement.matches(&local.version).unwrap_or(false)));
+
+            if let Some(remote) = replacement {
+                replacements.push( PackagePair { local: local.clone(), remote: remote.clone() } );
+            }
+        }
+
         for remote in packages.iter() {
             match self.find(remote) {
                 Some(local) => {
                     map.push( PackagePair { local: local.clone(), remote: remote.clone() } );
         
-                    if version_compare(&remote.version, &local.version) == std::cmp::Ordering::Greater{
-                        updates.push( PackagePair { local: local.clone(), remote: remote.clone() } );
+                    match version_compare(&remote.version, &local.version)? {
+                        std::cmp::Ordering::Greater => updates.push( PackagePair { local: local.clone(), remote: remote.clone() } ),
+                        std::cmp::Ordering::Less => downgrades.push( PackagePair { local: local.clone(), remote: remote.clone() } ),
+                        std::cmp::Ordering::Equal => { }
                     }
                 }
+                None if replacements.iter().any(|pair| pair.remote.name == remote.name) => { }
                 None => { 
                     not_installed.push(remote.clone());
                 }
             }
         }
 
-        CrossCheckSummary { 
+        Ok(CrossCheckSummary { 
             map,
             updates,
-            not_installed
-        }
+            downgrades,
+            not_installed,
+            replacements
+        })
     }
     
+    /// Remembers 'groups' as selected, ignoring the ones already remembered
+    pub(crate) fn add_groups(&mut self, groups: &[String]) {
+        for group in groups {
+            if !self.groups.contains(group) {
+                self.groups.push(group.clone());
+            }
+        }
+    }
+
+    /// Remembers 'paths' as left behind by an uninstallation. The ones removed since are forgotten
+    pub(crate) fn add_leftovers(&mut self, paths: &[PathBuf]) {
+        self.leftovers.retain(|path| path.exists());
+        for path in paths {
+            if !self.leftovers.contains(path) {
+                self.leftovers.push(path.clone());
+            }
+        }
+    }
+
     /// Gets installation metadata of installed packages
     pub fn get_packages(&self) -> &[PackageInstallation] {
         &self.packages
@@ -173,13 +245,68 @@ impl InstallationSummary {
         Err(PackageUninstallError::InstallationNotFound)
     }
     
+    /// Replaces the persisted summary atomically, keeping the previous one as a backup
     pub(super) fn save(&mut self) -> Result<&mut Self, SerializationError> {
-        let mut file = helpers::file::create(self.path.clone())?;
-        helpers::file::write_all_file(&mut file, serializer::to_json(&self.inner)?.as_bytes())?;
+        let json = serializer::to_json(&self.inner)?;
+        helpers::file::write_atomic(&self.path, json.as_bytes(), Some(&backup_path(&self.path)))?;
         Ok(self)
     }
 }
 
+/// Reads and migrates the summary at 'path', expanding its placeholders through 'formatter' if given. Unknown or malformed
+/// placeholders are reported rather than kept
+fn read_file(path: &Path, formatter: Option<&TemplateFormat>) -> Result<InstallitionSummaryInner, SerializationError> {
+    let json = helpers::file::read_to_string(path)?;
+    match formatter {
+        Some(formatter) => {
+            let json = formatter.format_strict(&json)
+                .inspect_err(|err| log::error!("Installation summary contains an invalid template. {:?}", err))?;
+            migrate(&json)
+        }
+        None => migrate(&json),
+    }
+}
+
+/// Reads the summary at 'path', falling back to its backup if it's missing or unreadable
+fn read_with_backup(path: &Path, formatter: Option<&TemplateFormat>) -> Result<InstallitionSummaryInner, SerializationError> {
+    match read_file(path, formatter) {
+        Ok(summary) => Ok(summary),
+        Err(err) => {
+            log::warn!("Failed to read installation summary, trying its backup. {:?}", err);
+            read_file(&backup_path(path), formatter).map_err(|_| err)
+        }
+    }
+}
+
+/// Gets the path the previous generation of the summary at 'path' is kept at
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(BACKUP_SUFFIX);
+    PathBuf::from(backup)
+}
+
+/// Deserializes a summary of any older layout, upgrading it to the current one
+fn migrate(json: &str) -> Result<InstallitionSummaryInner, SerializationError> {
+    let mut value: serde_json::Value = serializer::from_json(json)?;
+    let version = value.get("SchemaVersion").and_then(|v| v.as_u64()).unwrap_or(1).max(1) as u32;
+
+    if version > SUMMARY_SCHEMA_VERSION {
+        return Err(SerializationError::UnsupportedSchema(version));
+    }
+
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(&mut value);
+    }
+
+    if version != SUMMARY_SCHEMA_VERSION {
+        log::info!("Migrated installation summary from schema version {} to {}", version, SUMMARY_SCHEMA_VERSION);
+    }
+
+    let mut summary: InstallitionSummaryInner = serde_json::from_value(value)?;
+    summary.schema_version = SUMMARY_SCHEMA_VERSION;
+    Ok(summary)
+}
+
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "PascalCase")]
 pub struct PackageInstallation {
@@ -189,7 +316,19 @@ pub struct PackageInstallation {
     pub installed_at: chrono::DateTime<chrono::Local>,
     pub updated_at: chrono::DateTime<chrono::Local>,
     pub default: bool,
-    pub operations: OperationHistory
+    pub operations: OperationHistory,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+    /// 'InstallPath' of the package at the time it was installed
+    #[serde(default)]
+    pub install_path: String,
+    /// 'ConfigFiles' of the package at the time it was installed
+    #[serde(default)]
+    pub config_files: Vec<String>
 }
 
 impl fmt::Display for PackageInstallation {
@@ -208,6 +347,22 @@ impl fmt::Display for PackageInstallation {
 }
 
 impl PackageInstallation {
+    /// Builds a 'Package' stub for this installation, used when the package is no longer listed in the repository
+    pub fn to_package(&self) -> Package {
+        Package {
+            name: self.name.clone(),
+            display_name: self.display_name.clone(),
+            version: self.version.clone(),
+            default: self.default,
+            dependencies: self.dependencies.clone(),
+            conflicts: self.conflicts.clone(),
+            required: self.required,
+            install_path: self.install_path.clone(),
+            config_files: self.config_files.clone(),
+            ..Default::default()
+        }
+    }
+
     fn from_package(package: &Package) -> PackageInstallation {
         PackageInstallation {
             name: package.name.clone(),
@@ -217,6 +372,11 @@ impl PackageInstallation {
             installed_at: chrono::Local::now(),
             updated_at: chrono::Local::now(),
             operations: OperationHistory::default(),
+            dependencies: package.dependencies.clone(),
+            conflicts: package.conflicts.clone(),
+            required: package.required,
+            install_path: package.install_path.clone(),
+            config_files: package.config_files.clone(),
         }
     }
 }
@@ -252,7 +412,9 @@ impl fmt::Display for PackagePair {
 pub struct CrossCheckSummary {
     pub map: Vec<PackagePair>,
     pub updates: Vec<PackagePair>,
-    pub not_installed: Vec<Package>
+    pub downgrades: Vec<PackagePair>,
+    pub not_installed: Vec<Package>,
+    pub replacements: Vec<PackagePair>
 }
 
 impl fmt::Display for CrossCheckSummary {
@@ -269,11 +431,37 @@ impl fmt::Display for CrossCheckSummary {
             writeln!(f, "{}", pair)?;
         }
 
+        writeln!(f, "\nDowngrades Available:")?;
+        for pair in &self.downgrades {
+            writeln!(f, "{}", pair)?;
+        }
+
         writeln!(f, "\nPackages Not Installed:")?;
         for pkg in &self.not_installed {
             writeln!(f, "{}", pkg)?;
         }
 
+        writeln!(f, "\nReplaced Packages:")?;
+        for pair in &self.replacements {
+            writeln!(f, "{}", pair)?;
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate() {
+        let legacy = r#"{ "ApplicationName": "app", "Packages": [], "Operations": { "Records": [] } }"#;
+        let summary = migrate(legacy).unwrap();
+        assert_eq!(summary.schema_version, SUMMARY_SCHEMA_VERSION);
+        assert_eq!(summary.channel, None);
+
+        let newer = format!(r#"{{ "SchemaVersion": {}, "ApplicationName": "app", "Packages": [], "Operations": {{ "Records": [] }} }}"#, SUMMARY_SCHEMA_VERSION + 1);
+        assert!(matches!(migrate(&newer), Err(SerializationError::UnsupportedSchema(_))));
+    }
+}
@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::helpers::{self, file::IoError, serializer::SerializationError};
+
+use super::{app::InstallyApp, journal::{Journal, JournaledWorkload, JOURNAL_FILE_NAME}, operation::OperationRecord, package::Package, summary::InstallationSummary};
+
+/// Files removed during a workload are moved here, next to 'instally_summary.json', until the workload completes
+pub const BACKUP_DIRECTORY_NAME: &str = "instally_backup";
+
+/// A change made during a workload, undone when the workload rolls back
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase", rename_all_fields = "PascalCase")]
+pub enum TransactionStep {
+    /// An executed operation, 'package' is the one it was executed for
+    Operation { package: Option<Package>, record: OperationRecord },
+    /// A removed file, moved aside to 'backup'. Removed directories have no backup, they are created again
+    Removal { path: PathBuf, backup: Option<PathBuf> },
+}
+
+impl TransactionStep {
+    fn undo(&self, app: &InstallyApp) -> Result<(), rust_i18n::error::Error> {
+        match self {
+            TransactionStep::Operation { package, record } => {
+                record.into_operation(package.as_ref()).and_then(|mut operation| operation.revert(app, None))
+            }
+            TransactionStep::Removal { path, backup: None } => Ok(helpers::file::create_dir_all(path)?),
+            TransactionStep::Removal { path, backup: Some(backup) } => {
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    helpers::file::create_dir_all(parent)?;
+                }
+                Ok(helpers::file::move_file(backup, path)?)
+            }
+        }
+    }
+}
+
+/// Tracks the changes made during a workload so that a failed workload leaves nothing behind.
+/// Executed operations are reverted and removed files are put back in reverse order, then the summary is restored
+/// to the state the workload started with.
+///
+/// Changes are journaled next to the summary as well, so that a workload cut short by a crash can be rolled back on the next launch
+#[derive(Debug)]
+pub struct Transaction {
+    workload: JournaledWorkload,
+    snapshot: InstallationSummary,
+    steps: Vec<TransactionStep>,
+    journal: Option<Journal>,
+}
+
+impl Transaction {
+    /// Starts a transaction of 'workload' over the current state of 'summary'
+    pub fn begin(workload: JournaledWorkload, summary: InstallationSummary) -> Self {
+        Transaction { workload, snapshot: summary, steps: Vec::new(), journal: None }
+    }
+
+    /// Carries on with the transaction of an interrupted 'workload' over the state of 'summary' it began with.
+    /// Its journaled 'steps' are undone along with the new ones on rollback
+    pub fn resume(workload: JournaledWorkload, summary: InstallationSummary, steps: Vec<TransactionStep>) -> Result<Self, SerializationError> {
+        let journal = Journal::resume(journal_path(&summary), &workload, &steps)?;
+        Ok(Transaction { workload, snapshot: summary, steps, journal: Some(journal) })
+    }
+
+    /// Checks whether the transaction was begun for 'workload'
+    pub fn is_of(&self, workload: &JournaledWorkload) -> bool {
+        &self.workload == workload
+    }
+
+    /// Adds an executed operation, 'target' is the package it was executed for
+    pub fn add(&mut self, target: Option<&Package>, record: OperationRecord) -> Result<(), SerializationError> {
+        self.record(TransactionStep::Operation { package: target.cloned(), record })
+    }
+
+    /// Moves the file at 'path' to the backup directory, it's put back if the workload rolls back
+    pub fn remove_file(&mut self, path: &Path) -> Result<(), SerializationError> {
+        let directory = backup_directory(&self.snapshot);
+        helpers::file::create_dir_all(&directory)?;
+
+        if !path.is_file() && !path.is_symlink() {
+            return Err(IoError::from(std::io::Error::from(std::io::ErrorKind::NotFound)).into());
+        }
+
+        // journaled ahead of the move, a crash in between can't lose the file
+        let backup = directory.join(self.steps.len().to_string());
+        self.record(TransactionStep::Removal { path: path.to_path_buf(), backup: Some(backup.clone()) })?;
+        Ok(helpers::file::move_file(path, &backup)?)
+    }
+
+    /// Removes the empty directory at 'path', it's created again if the workload rolls back
+    pub fn remove_dir(&mut self, path: &Path) -> Result<(), SerializationError> {
+        std::fs::remove_dir(path).map_err(IoError::from)?;
+        self.record(TransactionStep::Removal { path: path.to_path_buf(), backup: None })
+    }
+
+    /// Keeps the changes made during the workload. The summary has to be persisted by then
+    pub fn commit(self) {
+        self.close();
+    }
+
+    /// Undoes the changes made during the workload, latest first, then restores the summary exactly as it was
+    pub fn rollback(self, app: &InstallyApp) {
+        log::info!("Rolling back {} change(s).", self.steps.len());
+        undo_steps(app, &self.steps);
+
+        app.modify_summary(|summary| *summary = self.snapshot.clone());
+        self.close();
+    }
+
+    /// The journal is created along with the first step, workloads that don't change anything leave none behind
+    fn record(&mut self, step: TransactionStep) -> Result<(), SerializationError> {
+        let journal = match self.journal.as_mut() {
+            Some(journal) => journal,
+            None => self.journal.insert(Journal::create(journal_path(&self.snapshot), &self.workload)?),
+        };
+
+        journal.append(&step)?;
+        self.steps.push(step);
+        Ok(())
+    }
+
+    fn close(self) {
+        remove_backup_directory(&self.snapshot);
+
+        if let Some(journal) = self.journal {
+            if let Err(err) = journal.remove() {
+                log::error!("Failed to remove the workload journal. {:?}", err);
+            }
+        }
+    }
+}
+
+/// Gets the path of the journal kept along with 'summary'
+pub fn journal_path(summary: &InstallationSummary) -> PathBuf {
+    summary.get_path().with_file_name(JOURNAL_FILE_NAME)
+}
+
+/// Gets the directory files removed during a workload are moved to, kept along with 'summary'
+pub fn backup_directory(summary: &InstallationSummary) -> PathBuf {
+    summary.get_path().with_file_name(BACKUP_DIRECTORY_NAME)
+}
+
+/// Removes the backup directory once the files in it are either put back or no longer needed
+pub(crate) fn remove_backup_directory(summary: &InstallationSummary) {
+    match std::fs::remove_dir_all(backup_directory(summary)) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => log::error!("Failed to remove the backup directory. {:?}", err),
+        _ => (),
+    }
+}
+
+/// Undoes 'steps', latest first. Failing to undo a step doesn't stop the rest from being undone
+pub(crate) fn undo_steps(app: &InstallyApp, steps: &[TransactionStep]) {
+    for step in steps.iter().rev() {
+        if let Err(err) = step.undo(app) {
+            log::error!("Failed to roll back {:?}. {:?}", step, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{definitions::{operation::Operation, product::Product}, workloads::operations::createfile::CreateFileOperation};
+
+    use super::*;
+
+    fn create_file(app: &InstallyApp, path: &Path) -> Result<(), rust_i18n::error::Error> {
+        Operation::from_performer(Box::new(CreateFileOperation::new(&path.to_path_buf()))).execute(app, None)
+    }
+
+    #[test]
+    fn test_rollback() {
+        let dir = tempfile::tempdir().unwrap();
+        let product = Product::new("app", "App", "publisher", "", "", "", dir.path().to_str().unwrap());
+        let app = InstallyApp::default_with_product(&product);
+        app.modify_summary(|summary| *summary = InstallationSummary::default(&product));
+
+        let snapshot = app.get_summary();
+        app.resume_transaction(Transaction::begin(JournaledWorkload::Installer { target_packages: None }, snapshot.clone()));
+
+        let first = dir.path().join("first.txt");
+        let second = dir.path().join("second.txt");
+        create_file(&app, &first).unwrap();
+        create_file(&app, &second).unwrap();
+
+        // its directory doesn't exist
+        assert!(create_file(&app, &dir.path().join("missing").join("third.txt")).is_err());
+        assert_eq!(app.get_summary().operations.get_records().len(), 2);
+        assert!(journal_path(&snapshot).is_file());
+
+        app.rollback_transaction();
+
+        assert!(!first.exists());
+        assert!(!second.exists());
+        assert_eq!(app.get_summary(), snapshot);
+        assert!(!journal_path(&snapshot).exists());
+        assert!(!backup_directory(&snapshot).exists());
+    }
+}
@@ -0,0 +1,50 @@
+use std::{fmt, path::{Path, PathBuf}};
+
+/// A file of an installed package that no longer is the way it was extracted. Paths are relative to the package directory
+#[derive(Clone, Debug, PartialEq)]
+pub enum FileIssue {
+    Missing(PathBuf),
+    Modified(PathBuf),
+}
+
+impl FileIssue {
+    pub fn get_path(&self) -> &Path {
+        match self {
+            FileIssue::Missing(path) | FileIssue::Modified(path) => path,
+        }
+    }
+}
+
+impl fmt::Display for FileIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileIssue::Missing(path) => write!(f, "missing: {}", path.display()),
+            FileIssue::Modified(path) => write!(f, "modified: {}", path.display()),
+        }
+    }
+}
+
+/// Files of an installed package that need to be repaired
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackageVerification {
+    pub name: String,
+    pub display_name: String,
+    pub version: String,
+    pub issues: Vec<FileIssue>,
+}
+
+impl PackageVerification {
+    /// Gets whether every file of the package is in place
+    pub fn is_intact(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for PackageVerification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.issues.len() {
+            0 => write!(f, "{} ({}) - intact", self.display_name, self.version),
+            n => write!(f, "{} ({}) - {} file(s) missing or modified", self.display_name, self.version, n),
+        }
+    }
+}
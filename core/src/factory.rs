@@ -1,14 +1,16 @@
 
-use std::{sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::Duration};
+use std::{fmt::Display, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread, time::Duration};
 
 use rust_i18n::error::ErrorDetails;
 
-use crate::{definitions::{app::InstallyApp, context::AppContextNotifiable}, extensions::future::FutureSyncExt, workloads::{installer::{InstallerOptions, InstallerWorkloadState, InstallerWrapper}, noop::{NoopOptions, NoopWorkloadState, NoopWrapper}, uninstaller::{UninstallerOptions, UninstallerWorkloadState, UninstallerWrapper}, updater::{UpdaterOptions, UpdaterWorkloadState, UpdaterWrapper}, workload::{Workload, WorkloadResult}}};
+use crate::{definitions::{app::InstallyApp, context::{AppContextNotifiable, AppWrapper}}, extensions::future::FutureSyncExt, workloads::{installer::{InstallerOptions, InstallerWorkloadState, InstallerWrapper}, noop::{NoopOptions, NoopWorkloadState, NoopWrapper}, repairer::{RepairerOptions, RepairerWorkloadState, RepairerWrapper}, uninstaller::{UninstallerOptions, UninstallerWorkloadState, UninstallerWrapper}, updater::{UpdaterOptions, UpdaterWorkloadState, UpdaterWrapper}, verifier::{VerifierOptions, VerifierWorkloadState, VerifierWrapper}, workload::{Workload, WorkloadResult}}};
 
 pub enum WorkloadKind {
     Installer(InstallerOptions),
     Updater(UpdaterOptions),
     Uninstaller(UninstallerOptions),
+    Verifier(VerifierOptions),
+    Repairer(RepairerOptions),
     Error(NoopOptions, ErrorDetails),
 }
 
@@ -72,6 +74,14 @@ fn spawn_workload(app: &InstallyApp, settings: WorkloadKind) -> tokio::task::Joi
             log::info!("Spawning uninstaller workload thread");
             uninstaller(UninstallerWrapper::new_with_opts(app.clone(), r))
         },
+        WorkloadKind::Verifier(r) => {
+            log::info!("Spawning verifier workload thread");
+            verifier(VerifierWrapper::new_with_opts(app.clone(), r))
+        },
+        WorkloadKind::Repairer(r) => {
+            log::info!("Spawning repairer workload thread");
+            repairer(RepairerWrapper::new_with_opts(app.clone(), r))
+        },
         WorkloadKind::Error(opt, err) => {
             failure(err, NoopWrapper::new_with_opts(app.clone(), opt))
         }
@@ -167,6 +177,49 @@ fn uninstaller(mut wrapper: UninstallerWrapper) -> tokio::task::JoinHandle<Workl
     })
 }
 
+fn verifier(wrapper: VerifierWrapper) -> tokio::task::JoinHandle<WorkloadResult> {
+    run_to_completion("verifier", wrapper, VerifierWorkloadState::Done, VerifierWorkloadState::Interrupted)
+}
+
+fn repairer(wrapper: RepairerWrapper) -> tokio::task::JoinHandle<WorkloadResult> {
+    run_to_completion("repairer", wrapper, RepairerWorkloadState::Done, RepairerWorkloadState::Interrupted)
+}
+
+/// Runs 'wrapper' and finalizes it, reporting the outcome through the 'done' state or the one 'interrupted' builds
+fn run_to_completion<T, S>(name: &'static str, mut wrapper: AppWrapper<T>, done: S, interrupted: fn(ErrorDetails) -> S) -> tokio::task::JoinHandle<WorkloadResult>
+where
+    T: Default + Clone + Send + 'static,
+    AppWrapper<T>: Workload,
+    S: Display + Send + 'static,
+{
+    tokio::spawn(async move {
+        log::info!("Running {} workload", name);
+        let workload_result = wrapper.run().await;
+
+        log::info!("Finalizing {} workload", name);
+        let finalize_result = wrapper.finalize(workload_result.is_err()).wait(); // TODO: impl send + sync for err type
+
+        match (workload_result, finalize_result) {
+            (Ok(()), Ok(())) => {
+                log::info!("Workload completed");
+
+                let result = WorkloadResult::Ok;
+                wrapper.app.set_workload_state(done);
+                wrapper.app.set_result(&result);
+                result
+            },
+            (Err(err), _) | (_, Err(err)) => {
+                log::error!("Workload failed. \n{err:?}");
+
+                let result = WorkloadResult::Error(err.get_details().to_owned());
+                wrapper.app.set_workload_state(interrupted(err.get_details().to_owned()));
+                wrapper.app.set_result(&result);
+                result
+            },
+        }
+    })
+}
+
 fn failure(err: ErrorDetails, mut wrapper: NoopWrapper) -> tokio::task::JoinHandle<WorkloadResult> {
     tokio::spawn(async move { 
         log::info!("Could not initiate a workload. {:?}", err);
@@ -0,0 +1,170 @@
+use std::{fmt, fs::File, io::{Seek, SeekFrom}, str::FromStr};
+
+use convert_case::*;
+use rust_i18n::error::*;
+use serde::{Deserialize, Serialize};
+use sha1::Digest;
+
+use crate::{*, helpers};
+
+use super::file::IoError;
+
+#[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
+pub enum ChecksumError {
+    #[error("io-error")]
+    Io(#[from] IoError),
+}
+
+/// Hash algorithm of a 'Checksum'. SHA-1 is only kept to read older repositories
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum ChecksumAlgorithm {
+    Sha1,
+    #[default]
+    Sha256,
+    Blake3,
+}
+
+impl ChecksumAlgorithm {
+    /// Gets extension of the side file the digest is written to
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha1 => "sha1",
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Blake3 => "blake3",
+        }
+    }
+}
+
+impl FromStr for ChecksumAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "").as_str() {
+            "sha1" => Ok(ChecksumAlgorithm::Sha1),
+            "sha256" => Ok(ChecksumAlgorithm::Sha256),
+            "blake3" => Ok(ChecksumAlgorithm::Blake3),
+            other => Err(format!("unsupported checksum algorithm '{}'", other)),
+        }
+    }
+}
+
+impl fmt::Display for ChecksumAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get_extension())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct Checksum {
+    pub algorithm: ChecksumAlgorithm,
+    /// Lowercase hex encoded digest
+    pub digest: String,
+}
+
+enum Hasher {
+    Sha1(sha1::Sha1),
+    Sha256(sha2::Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Sha1 => Hasher::Sha1(sha1::Sha1::new()),
+            ChecksumAlgorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            ChecksumAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Blake3(hasher) => { hasher.update(data); },
+        }
+    }
+
+    fn finalize(self) -> String {
+        match self {
+            Hasher::Sha1(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+impl Checksum {
+    pub fn new(algorithm: ChecksumAlgorithm, digest: &str) -> Self {
+        Checksum { algorithm, digest: digest.to_lowercase() }
+    }
+
+    /// Generates checksum of the file at 'path'
+    pub fn generate<P: AsRef<std::path::Path>>(algorithm: ChecksumAlgorithm, path: P) -> Result<Self, ChecksumError> {
+        let mut file = helpers::file::open(path)?;
+        Self::generate_file(algorithm, &mut file)
+    }
+
+    /// Generates checksum of the whole 'file', regardless of its current position
+    pub fn generate_file(algorithm: ChecksumAlgorithm, file: &mut File) -> Result<Self, ChecksumError> {
+        file.seek(SeekFrom::Start(0)).map_err(IoError::from)?;
+
+        let mut hasher = Hasher::new(algorithm);
+        let mut buffer = [0; 8192];
+
+        loop {
+            let size = helpers::file::read(file, &mut buffer)?;
+
+            if size == 0 {
+                break;
+            }
+            hasher.update(&buffer[..size]);
+        }
+
+        file.seek(SeekFrom::Start(0)).map_err(IoError::from)?;
+        Ok(Checksum { algorithm, digest: hasher.finalize() })
+    }
+
+    /// Checks whether 'file' matches this checksum, returning the computed one as well
+    pub fn verify_file(&self, file: &mut File) -> Result<(bool, Checksum), ChecksumError> {
+        let check = Self::generate_file(self.algorithm, file)?;
+        Ok((check.digest.eq_ignore_ascii_case(&self.digest), check))
+    }
+
+    /// Writes the digest next to 'path' as a side file, such as 'package.zip.sha256'
+    pub fn writeout<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), ChecksumError> {
+        let side_file = format!("{}.{}", path.as_ref().to_str().unwrap(), self.algorithm.get_extension());
+        Ok(helpers::file::write_all(side_file, self.digest.as_bytes())?)
+    }
+}
+
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_generate_and_verify() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"abc").unwrap();
+
+        let sha256 = Checksum::generate_file(ChecksumAlgorithm::Sha256, &mut file).unwrap();
+        assert_eq!(sha256.digest, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        let sha1 = Checksum::generate_file(ChecksumAlgorithm::Sha1, &mut file).unwrap();
+        assert_eq!(sha1.digest, "a9993e364706816aba3e25717850c26c9cd0d89d");
+
+        let blake3 = Checksum::generate_file(ChecksumAlgorithm::Blake3, &mut file).unwrap();
+        assert_eq!(blake3.digest, "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+
+        assert!(Checksum::new(ChecksumAlgorithm::Sha256, &sha256.digest.to_uppercase()).verify_file(&mut file).unwrap().0);
+        assert!(!Checksum::new(ChecksumAlgorithm::Blake3, &sha256.digest).verify_file(&mut file).unwrap().0);
+    }
+}
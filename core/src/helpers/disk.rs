@@ -0,0 +1,23 @@
+use std::path::{Path, PathBuf};
+
+use sysinfo::Disks;
+
+/// Volume a path resides on, with its available space in bytes
+#[derive(Clone, Debug, PartialEq)]
+pub struct Volume {
+    pub mount_point: PathBuf,
+    pub available_space: u64,
+}
+
+/// Gets the volume 'path' resides on. 'path' doesn't need to exist yet, its closest existing ancestor is used
+pub fn get_volume<P: AsRef<Path>>(path: P) -> Option<Volume> {
+    let existing = path.as_ref().ancestors().find(|p| p.exists())?;
+    let existing = std::fs::canonicalize(existing).ok()?;
+    let existing = super::file::strip_extended_length_prefix(&existing);
+
+    let disks = Disks::new_with_refreshed_list();
+    disks.list().iter()
+        .filter(|disk| existing.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| Volume { mount_point: disk.mount_point().to_path_buf(), available_space: disk.available_space() })
+}
@@ -10,6 +10,12 @@ use rust_i18n::error::*;
 #[error("io-error.{}", .0.kind().to_string().to_case(Case::Kebab))]
 pub struct IoError(#[from] std::io::Error);
 
+impl IoError {
+    pub fn kind(&self) -> std::io::ErrorKind {
+        self.0.kind()
+    }
+}
+
 pub fn cwd() -> Result<PathBuf, IoError> {
     let cwd = std::env::current_exe()?;
     let canonicalized = std::fs::canonicalize(&cwd)?;
@@ -38,6 +44,13 @@ pub fn create<P: AsRef<Path>>(path: P) -> Result<File, IoError> {
     Ok(file)
 }
 
+pub fn open_append<P: AsRef<Path>>(path: P) -> Result<File, IoError> {
+    let file = OpenOptions::new()
+        .append(true).open(path.as_ref())?;
+
+    Ok(file)
+}
+
 pub fn copy_file<P: AsRef<Path>>(from: P, to: P) -> Result<u64, IoError> {
     Ok(std::fs::copy(from, to)?)
 }
@@ -50,6 +63,16 @@ pub fn delete<P: AsRef<Path>>(from: P) -> Result<(), IoError> {
     Ok(std::fs::remove_file(from)?)
 }
 
+/// Moves the file at 'from' to 'to', replacing it. Files on another volume are copied over and removed
+pub fn move_file<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> Result<(), IoError> {
+    if std::fs::rename(from.as_ref(), to.as_ref()).is_ok() {
+        return Ok(());
+    }
+
+    std::fs::copy(from.as_ref(), to.as_ref())?;
+    Ok(std::fs::remove_file(from)?)
+}
+
 pub fn read_to_string<P: AsRef<Path>>(path: P) -> Result<String, IoError> {
     Ok(std::fs::read_to_string(path)?)
 }
@@ -58,6 +81,31 @@ pub fn write_all<P: AsRef<Path>>(path: P, buffer: &[u8]) -> Result<(), IoError>{
     Ok(write_all_file(&mut create(path)?, buffer)?)
 }
 
+/// Replaces the file at 'path' with 'buffer' through a synced temporary file, so that it's never left partially written.
+/// The previous content is copied to 'backup' first, if given
+pub fn write_atomic<P: AsRef<Path>>(path: P, buffer: &[u8], backup: Option<&Path>) -> Result<(), IoError> {
+    let path = path.as_ref();
+    let directory = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+
+    let mut tmp = tempfile::NamedTempFile::new_in(directory)?;
+    tmp.write_all(buffer)?;
+    tmp.as_file().sync_all()?;
+
+    if let Some(backup) = backup {
+        if path.exists() {
+            std::fs::copy(path, backup)?;
+        }
+    }
+
+    tmp.persist(path).map_err(|err| err.error)?;
+
+    // the rename itself is only durable once the directory is synced
+    #[cfg(not(target_os = "windows"))]
+    _ = File::open(directory).and_then(|dir| dir.sync_all());
+
+    Ok(())
+}
+
 pub fn write_all_stream(writer: &mut impl Write, buffer: &[u8]) -> Result<(), IoError> {
     Ok(std::io::Write::write_all(writer, buffer)?)
 }
@@ -86,7 +134,7 @@ pub fn path(file: &mut File) -> Result<PathBuf, IoError> {
     Ok(file.path()?)
 }
 
-fn strip_extended_length_prefix(path: &Path) -> PathBuf {
+pub(crate) fn strip_extended_length_prefix(path: &Path) -> PathBuf {
     const VERBATIM_PREFIX: &str = r"\\?\";
     let path_str = path.to_str().unwrap_or_default();
     if path_str.starts_with(VERBATIM_PREFIX) {
@@ -1,17 +1,81 @@
 use std::collections::HashMap;
 
+use crate::*;
+
+use rust_i18n::error::*;
+use convert_case::*;
+
+#[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
+pub enum TemplateError {
+    #[error("unresolved")]
+    Unresolved(String),
+
+    #[error("unterminated")]
+    Unterminated(usize),
+}
+
+/// Replaces placeholders of 'pattern' in 'template' in a single pass, leaving unknown ones as they are
 pub fn format_string(template: &str, replacements: &HashMap<String, String>, pattern: &str) -> String {
-    let mut result = template.to_string();
-    for (key, value) in replacements {
-        let placeholder = pattern.replace("{}", key);
-        result = result.replace(&placeholder, value);
+    render(template, pattern, |key| replacements.get(key).cloned(), false)
+        .unwrap_or_else(|_| template.to_owned())
+}
+
+/// Expands placeholders of 'pattern', such as '@{Key}', in a single pass so values are never expanded again.
+///
+/// '@{Key|default}' falls back to 'default' when 'resolve' doesn't know the key. Doubling the first character of the
+/// pattern, '@@{', yields a literal '@{'. Unresolved and unterminated placeholders are errors in 'strict' mode and are
+/// kept as they are otherwise
+fn render<R>(template: &str, pattern: &str, resolve: R, strict: bool) -> Result<String, TemplateError>
+where R: Fn(&str) -> Option<String>
+{
+    let (prefix, suffix) = pattern.split_once("{}").unwrap_or((pattern, ""));
+    let escape = prefix.chars().next();
+
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find(prefix) {
+        if let Some(escape) = escape.filter(|c| rest[..start].ends_with(*c)) {
+            result.push_str(&rest[..start - escape.len_utf8()]);
+            result.push_str(prefix);
+            rest = &rest[start + prefix.len()..];
+            continue;
+        }
+
+        result.push_str(&rest[..start]);
+        let body_start = start + prefix.len();
+
+        let Some(length) = rest[body_start..].find(suffix) else {
+            if strict {
+                return Err(TemplateError::Unterminated(template.len() - rest.len() + start));
+            }
+            break;
+        };
+
+        let body = &rest[body_start..body_start + length];
+        let (key, default) = match body.split_once('|') {
+            Some((key, default)) => (key, Some(default)),
+            None => (body, None),
+        };
+
+        match resolve(key).or(default.map(str::to_owned)) {
+            Some(value) => result.push_str(&value),
+            None if strict => return Err(TemplateError::Unresolved(key.to_owned())),
+            None => result.push_str(&rest[start..body_start + length + suffix.len()]),
+        }
+
+        rest = &rest[body_start + length + suffix.len()..];
     }
-    result
+
+    result.push_str(rest);
+    Ok(result)
 }
 
 pub struct TemplateFormat {
     replacements: HashMap<String, String>,
     value_transformer: Option<Box<dyn Fn(&str) -> String>>,
+    /// Resolves 'Env.' keys, the process environment unless replaced by 'with_env'
+    env: Box<dyn Fn(&str) -> Option<String>>,
     pattern: String,
 }
 
@@ -20,6 +84,7 @@ impl TemplateFormat {
         Self {
             replacements: HashMap::new(),
             value_transformer,
+            env: Box::new(|name| std::env::var(name).ok()),
             pattern: "@{{}}".to_string(),
         }
     }
@@ -28,30 +93,56 @@ impl TemplateFormat {
         Self {
             replacements: HashMap::new(),
             value_transformer,
+            env: Box::new(|name| std::env::var(name).ok()),
             pattern: pattern.to_string(),
         }
     }
 
     pub fn add_replacement(mut self, key: &str, value: &str) -> Self {
-        let transformed_value = self.value_transformer.as_ref().map_or(value.to_owned(), |transformer| transformer(value));
+        let transformed_value = self.transform(value);
         self.replacements.insert(key.to_string(), transformed_value);
         self
     }
 
+    /// Resolves 'Env.' keys through 'env' instead of the process environment
+    pub fn with_env<E>(mut self, env: E) -> Self
+    where E: Fn(&str) -> Option<String> + 'static
+    {
+        self.env = Box::new(env);
+        self
+    }
+
+    /// Expands placeholders of the template, keeping unresolved ones as they are
     pub fn format(&self, template: &str) -> String {
-        format_string(template, &self.replacements, &self.pattern)
+        self.render(template, false).unwrap_or_else(|_| template.to_owned())
+    }
+
+    /// Expands placeholders of the template, failing on unresolved or unterminated ones
+    pub fn format_strict(&self, template: &str) -> Result<String, TemplateError> {
+        self.render(template, true)
     }
 
     pub fn iter(&self) -> std::collections::hash_map::Iter<'_, String, String> {
         self.replacements.iter()
     }
+
+    fn render(&self, template: &str, strict: bool) -> Result<String, TemplateError> {
+        render(template, &self.pattern, |key| match key.strip_prefix("Env.") {
+            Some(name) => (self.env)(name).map(|value| self.transform(&value)),
+            None => self.replacements.get(key).cloned(),
+        }, strict)
+    }
+
+    fn transform(&self, value: &str) -> String {
+        self.value_transformer.as_ref().map_or(value.to_owned(), |transformer| transformer(value))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
-    use crate::helpers::formatter::format_string;
+    use crate::helpers::formatter::{format_string, TemplateError, TemplateFormat};
 
     #[test]
     fn test_formatter() {
@@ -86,4 +177,24 @@ mod tests {
         assert_eq!(r, expected, "Template formatter failed for xml");
         assert_eq!(r2, expected_json, "Template formatter failed for json");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_template_syntax() {
+        let format = TemplateFormat::new(None)
+            .add_replacement("App.Name", "@{App.Publisher}")
+            .add_replacement("App.Publisher", "liteware")
+            .with_env(|name| (name == "INSTALLY_FORMATTER_TEST").then(|| "value".to_owned()));
+
+        assert_eq!(format.format("@{App.Name} by @{App.Publisher}"), "@{App.Publisher} by liteware");
+        assert_eq!(format.format("@{App.Channel|stable}, @{App.Publisher|none}"), "stable, liteware");
+        assert_eq!(format.format("@@{App.Name} @{Unknown}"), "@{App.Name} @{Unknown}");
+
+        assert_eq!(format.format("@{Env.INSTALLY_FORMATTER_TEST}"), "value");
+        assert_eq!(format.format("@{Env.INSTALLY_FORMATTER_UNSET}"), "@{Env.INSTALLY_FORMATTER_UNSET}");
+
+        assert!(matches!(format.format_strict("@{Unknown}"), Err(TemplateError::Unresolved(key)) if key == "Unknown"));
+        assert!(matches!(format.format_strict("text @{App.Name"), Err(TemplateError::Unterminated(5))));
+        assert!(format.format_strict("@@{Unknown}").is_ok());
+        assert_eq!(format.format_strict("@{Unknown|}").unwrap(), "");
+    }
+}
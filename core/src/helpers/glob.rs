@@ -0,0 +1,61 @@
+use std::path::Path;
+
+/// Matches 'path', relative to a package directory, against a glob 'pattern'. '*' and '?' stay within a path
+/// segment, '**' spans any number of directories. Patterns without a '/', such as '*.ini', match file names at any depth
+pub fn matches_path(pattern: &str, path: &Path) -> bool {
+    let path = path.components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+
+    let pattern = match pattern.contains('/') {
+        true => pattern.to_owned(),
+        false => format!("**/{}", pattern),
+    };
+
+    let pattern = pattern.split('/').filter(|s| !s.is_empty() && *s != ".").collect::<Vec<_>>();
+    let path = path.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(&"**"), _) => match_segments(&pattern[1..], path) || (!path.is_empty() && match_segments(pattern, &path[1..])),
+        (Some(p), Some(s)) => {
+            let p = p.chars().collect::<Vec<_>>();
+            let s = s.chars().collect::<Vec<_>>();
+            match_segment(&p, &s) && match_segments(&pattern[1..], &path[1..])
+        }
+        _ => false,
+    }
+}
+
+fn match_segment(pattern: &[char], text: &[char]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some('*'), _) => match_segment(&pattern[1..], text) || (!text.is_empty() && match_segment(pattern, &text[1..])),
+        (Some('?'), Some(_)) => match_segment(&pattern[1..], &text[1..]),
+        (Some(p), Some(t)) if p == t => match_segment(&pattern[1..], &text[1..]),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_path() {
+        assert!(matches_path("*.ini", Path::new("settings.ini")));
+        assert!(matches_path("*.ini", Path::new("conf/settings.ini")));
+        assert!(!matches_path("*.ini", Path::new("settings.ini.new")));
+
+        assert!(matches_path("conf/*.json", Path::new("conf/app.json")));
+        assert!(!matches_path("conf/*.json", Path::new("conf/nested/app.json")));
+        assert!(matches_path("conf/**/*.json", Path::new("conf/nested/app.json")));
+        assert!(matches_path("conf/**", Path::new("conf/a/b")));
+
+        assert!(matches_path("app.???", Path::new("app.cfg")));
+        assert!(!matches_path("app.cfg", Path::new("bin/app.cfg/x")));
+    }
+}
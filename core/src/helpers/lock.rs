@@ -0,0 +1,235 @@
+use std::{io::{ErrorKind, Write}, path::{Path, PathBuf}, time::Duration};
+
+use sysinfo::{Pid, ProcessesToUpdate, System};
+
+use crate::*;
+
+use rust_i18n::error::*;
+use convert_case::*;
+
+use super::file::IoError;
+
+/// Kept in the target directory while a process works on the installation
+pub const LOCK_FILE_NAME: &str = "instally.lock";
+
+/// A lock file without a readable owner is taken as held for this long after it was last written
+const GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+#[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
+pub enum LockError {
+    #[error("held")]
+    Held(u32),
+
+    #[error("pending")]
+    Pending,
+
+    #[error("{}", .0.get_message_key())]
+    Io(#[from] IoError),
+}
+
+/// Process holding the lock. Pids are reused, so the start time tells a live owner apart from a dead one
+#[derive(Clone, Debug, PartialEq)]
+struct LockOwner {
+    pid: u32,
+    /// 'None' where the start time of a process can't be read, written as '-'
+    start_time: Option<u64>,
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        let pid = std::process::id();
+        let start_time = probe(pid).flatten();
+        if start_time.is_none() {
+            log::warn!("Start time of the current process can't be read, the lock can't be told apart from one of a reused pid.");
+        }
+
+        LockOwner { pid, start_time }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        let (pid, start_time) = text.trim().split_once(' ')?;
+        let start_time = match start_time {
+            // written by versions that stored an unreadable start time as zero
+            "-" | "0" => None,
+            start_time => Some(start_time.parse().ok()?),
+        };
+
+        Some(LockOwner { pid: pid.parse().ok()?, start_time })
+    }
+
+    fn format(&self) -> String {
+        match self.start_time {
+            Some(start_time) => format!("{} {}", self.pid, start_time),
+            None => format!("{} -", self.pid),
+        }
+    }
+
+    /// Without a start time on either side, a running process of the same pid is taken as the owner
+    /// rather than risking to take over the lock of a live one
+    fn is_alive(&self) -> bool {
+        match (probe(self.pid), self.start_time) {
+            (None, _) => false,
+            (Some(Some(actual)), Some(recorded)) => actual == recorded,
+            (Some(_), _) => {
+                log::warn!("Can't tell whether process {} still owns the lock, taking it as alive.", self.pid);
+                true
+            }
+        }
+    }
+}
+
+/// Advisory lock over an installation, released when dropped. Locks are reentrant within a process,
+/// only the first one acquired removes the lock file
+#[derive(Debug)]
+pub struct InstallationLock {
+    path: PathBuf,
+    owned: bool,
+}
+
+impl Drop for InstallationLock {
+    fn drop(&mut self) {
+        if self.owned {
+            if let Err(err) = std::fs::remove_file(&self.path) {
+                log::error!("Failed to release lock {:?}. {}", self.path, err);
+            }
+        }
+    }
+}
+
+/// Locks the installation at 'directory' for the current process. A lock left behind by a process that is no longer running is taken over
+pub fn acquire<P: AsRef<Path>>(directory: P) -> Result<InstallationLock, LockError> {
+    let directory = directory.as_ref();
+    std::fs::create_dir_all(directory).map_err(IoError::from)?;
+
+    let path = directory.join(LOCK_FILE_NAME);
+    let current = LockOwner::current();
+
+    // a second attempt follows the removal of a stale lock
+    for _ in 0..2 {
+        match create(&path, &current) {
+            Ok(()) => {
+                log::info!("Installation locked, {:?}", path);
+                return Ok(InstallationLock { path, owned: true });
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                if is_owned_by(&path, &current)? {
+                    return Ok(InstallationLock { path, owned: false });
+                }
+
+                log::warn!("Taking over stale lock {:?}, its owner is not running.", path);
+                match std::fs::remove_file(&path) {
+                    Err(err) if err.kind() != ErrorKind::NotFound => return Err(IoError::from(err).into()),
+                    _ => (),
+                }
+            }
+            Err(err) => return Err(IoError::from(err).into()),
+        }
+    }
+
+    Err(IoError::from(std::io::Error::from(ErrorKind::AlreadyExists)).into())
+}
+
+/// Checks that no other running process holds the lock of the installation at 'directory', without taking it
+pub fn check<P: AsRef<Path>>(directory: P) -> Result<(), LockError> {
+    is_owned_by(&directory.as_ref().join(LOCK_FILE_NAME), &LockOwner::current()).map(|_| ())
+}
+
+/// Writes 'owner' to a temporary file and links it into place, the lock never exists without its owner written in it.
+/// Fails with 'AlreadyExists' if there is a lock already
+fn create(path: &Path, owner: &LockOwner) -> std::io::Result<()> {
+    let directory = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+
+    let mut tmp = tempfile::NamedTempFile::new_in(directory)?;
+    tmp.write_all(owner.format().as_bytes())?;
+    tmp.as_file().sync_all()?;
+
+    std::fs::hard_link(tmp.path(), path)
+}
+
+/// Checks whether the lock at 'path' is held by 'current'. 'false' if it's missing or its owner is not running anymore,
+/// fails if it's held by another process
+fn is_owned_by(path: &Path, current: &LockOwner) -> Result<bool, LockError> {
+    let owner = std::fs::read_to_string(path).ok().and_then(|text| LockOwner::parse(&text));
+    match owner {
+        Some(owner) if owner == *current => Ok(true),
+        Some(owner) if owner.is_alive() => Err(LockError::Held(owner.pid)),
+        Some(_) => Ok(false),
+        // written by something else than 'create' or corrupted, its owner might still be working
+        None if !is_expired(path) => Err(LockError::Pending),
+        None => Ok(false),
+    }
+}
+
+/// Checks whether the lock at 'path' was last written longer than the grace period ago, or is gone already
+fn is_expired(path: &Path) -> bool {
+    match std::fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => modified.elapsed().is_ok_and(|elapsed| elapsed > GRACE_PERIOD),
+        Err(_) => true,
+    }
+}
+
+/// Looks the process 'pid' up, 'None' if it's not running. Otherwise gets its start time, 'None' if it can't be read
+fn probe(pid: u32) -> Option<Option<u64>> {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    system.process(pid).map(|process| Some(process.start_time()).filter(|start_time| *start_time != 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let lock = acquire(dir.path()).unwrap();
+        assert!(lock.owned);
+
+        // same process, reentrant
+        let reentrant = acquire(dir.path()).unwrap();
+        assert!(!reentrant.owned);
+        drop(reentrant);
+        assert!(dir.path().join(LOCK_FILE_NAME).exists());
+
+        drop(lock);
+        assert!(!dir.path().join(LOCK_FILE_NAME).exists());
+
+        // dead owner
+        std::fs::write(dir.path().join(LOCK_FILE_NAME), format!("{} 0", u32::MAX - 1)).unwrap();
+        assert!(acquire(dir.path()).unwrap().owned);
+    }
+
+    #[test]
+    fn test_acquire_live_owner() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // the test runner outlives the test
+        let pid = Pid::from_u32(std::process::id());
+        let mut system = System::new();
+        system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        let parent = system.process(pid).and_then(|process| process.parent()).unwrap().as_u32();
+
+        let owner = LockOwner { pid: parent, start_time: probe(parent).flatten() };
+        std::fs::write(dir.path().join(LOCK_FILE_NAME), owner.format()).unwrap();
+
+        assert!(matches!(acquire(dir.path()), Err(LockError::Held(pid)) if pid == parent));
+        assert!(matches!(check(dir.path()), Err(LockError::Held(pid)) if pid == parent));
+        assert!(dir.path().join(LOCK_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn test_acquire_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(LOCK_FILE_NAME);
+
+        // its owner might be about to write to it
+        std::fs::write(&path, "").unwrap();
+        assert!(matches!(acquire(dir.path()), Err(LockError::Pending)));
+
+        let modified = std::time::SystemTime::now() - GRACE_PERIOD * 2;
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        assert!(acquire(dir.path()).unwrap().owned);
+    }
+}
@@ -6,4 +6,10 @@ pub mod serializer;
 pub mod tmp;
 pub mod file;
 pub mod workflow;
-pub mod sha1;
\ No newline at end of file
+pub mod signature;
+pub mod checksum;
+pub mod platform;
+pub mod disk;
+pub mod xdg;
+pub mod lock;
+pub mod glob;
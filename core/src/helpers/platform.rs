@@ -0,0 +1,92 @@
+use once_cell::sync::Lazy;
+
+use super::versioning::Version;
+
+static CURRENT: Lazy<Platform> = Lazy::new(|| Platform {
+    os: std::env::consts::OS.to_owned(),
+    arch: std::env::consts::ARCH.to_owned(),
+    os_version: sysinfo::System::os_version(),
+});
+
+/// Operating system and architecture packages are built for
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Platform {
+    pub os: String,
+    pub arch: String,
+    pub os_version: Option<String>,
+}
+
+impl Platform {
+    pub fn new(os: &str, arch: &str, os_version: Option<&str>) -> Self {
+        Platform { os: normalize_os(os), arch: normalize_arch(arch), os_version: os_version.map(|v| v.to_owned()) }
+    }
+
+    /// Gets the platform the application is running on
+    pub fn current() -> &'static Platform {
+        &CURRENT
+    }
+
+    /// Checks whether a package built for 'os' and 'arch', requiring at least 'min_os_version', runs on this platform.
+    /// Empty fields match any platform
+    pub fn supports(&self, os: &str, arch: &str, min_os_version: &str) -> bool {
+        if !os.is_empty() && normalize_os(os) != self.os {
+            return false;
+        }
+
+        if !arch.is_empty() && normalize_arch(arch) != self.arch {
+            return false;
+        }
+
+        if min_os_version.is_empty() {
+            return true;
+        }
+
+        // os versions are reported like '22.04' or '11 (22631)', only the leading part is compared
+        let current = self.os_version.as_deref()
+            .and_then(|v| v.split_whitespace().next())
+            .and_then(|v| Version::parse_lenient(v).ok());
+
+        match (current, Version::parse_lenient(min_os_version)) {
+            (Some(current), Ok(min)) => current >= min,
+            _ => {
+                log::warn!("Could not compare os version {:?} with the required {}, assuming it's supported.", self.os_version, min_os_version);
+                true
+            }
+        }
+    }
+}
+
+/// Maps common aliases such as 'win' or 'darwin' to the names of 'std::env::consts::OS'
+pub fn normalize_os(os: &str) -> String {
+    match os.to_lowercase().as_str() {
+        "win" | "win32" | "win64" => "windows".to_owned(),
+        "darwin" | "osx" | "mac" => "macos".to_owned(),
+        other => other.to_owned(),
+    }
+}
+
+/// Maps common aliases such as 'x64' or 'arm64' to the names of 'std::env::consts::ARCH'
+pub fn normalize_arch(arch: &str) -> String {
+    match arch.to_lowercase().as_str() {
+        "x64" | "amd64" | "x86-64" => "x86_64".to_owned(),
+        "arm64" => "aarch64".to_owned(),
+        "x32" | "i386" | "i686" => "x86".to_owned(),
+        other => other.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_supports() {
+        let platform = Platform::new("windows", "x86_64", Some("10 (19045)"));
+
+        assert!(platform.supports("", "", ""));
+        assert!(platform.supports("win", "x64", "10"));
+        assert!(!platform.supports("linux", "x86_64", ""));
+        assert!(!platform.supports("windows", "arm64", ""));
+        assert!(!platform.supports("windows", "x86_64", "11"));
+    }
+}
@@ -1,6 +1,6 @@
 use std::path::Path;
 
-use helpers::file::IoError;
+use helpers::{file::IoError, formatter::TemplateError};
 use serde::Deserialize;
 use rust_i18n::error::*;
 use convert_case::*;
@@ -13,6 +13,12 @@ pub enum SerializationError {
 
     #[error("json-error")]
     Engine(#[from] serde_json::error::Error),
+
+    #[error("unsupported-schema")]
+    UnsupportedSchema(u32),
+
+    #[error("{}", .0.get_message_key())]
+    Template(#[from] TemplateError),
 }
 
 pub fn to_json<T>(value: &T) -> Result<String, SerializationError>
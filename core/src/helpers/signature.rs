@@ -0,0 +1,73 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use convert_case::*;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rust_i18n::error::*;
+
+use crate::*;
+
+#[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
+pub enum SignatureError {
+    #[error("unsigned")]
+    Unsigned,
+
+    #[error("missing-key")]
+    MissingKey,
+
+    #[error("invalid-key")]
+    InvalidKey,
+
+    #[error("malformed")]
+    Malformed,
+
+    #[error("mismatch")]
+    Mismatch,
+
+    #[error("random")]
+    Random,
+}
+
+/// Generates a new Ed25519 key pair, returned as base64 encoded '(secret, public)' keys
+pub fn generate_keypair() -> Result<(String, String), SignatureError> {
+    let mut seed = [0u8; 32];
+    getrandom::getrandom(&mut seed).map_err(|_| SignatureError::Random)?;
+
+    let signing_key = SigningKey::from_bytes(&seed);
+    Ok((STANDARD.encode(signing_key.to_bytes()), STANDARD.encode(signing_key.verifying_key().to_bytes())))
+}
+
+/// Signs 'message' with a base64 encoded secret key, returning a base64 encoded detached signature
+pub fn sign(secret_key: &str, message: &[u8]) -> Result<String, SignatureError> {
+    let seed: [u8; 32] = decode_exact(secret_key).ok_or(SignatureError::InvalidKey)?;
+    let signing_key = SigningKey::from_bytes(&seed);
+
+    Ok(STANDARD.encode(signing_key.sign(message).to_bytes()))
+}
+
+/// Verifies a base64 encoded detached 'signature' of 'message' against a base64 encoded public key
+pub fn verify(public_key: &str, message: &[u8], signature: &str) -> Result<(), SignatureError> {
+    let key: [u8; 32] = decode_exact(public_key).ok_or(SignatureError::InvalidKey)?;
+    let key = VerifyingKey::from_bytes(&key).map_err(|_| SignatureError::InvalidKey)?;
+
+    let signature: [u8; 64] = decode_exact(signature).ok_or(SignatureError::Malformed)?;
+    key.verify(message, &Signature::from_bytes(&signature))
+        .map_err(|_| SignatureError::Mismatch)
+}
+
+fn decode_exact<const N: usize>(text: &str) -> Option<[u8; N]> {
+    STANDARD.decode(text.trim()).ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let (secret, public) = generate_keypair().unwrap();
+        let signature = sign(&secret, b"{\"ApplicationName\":\"test\"}").unwrap();
+
+        assert!(verify(&public, b"{\"ApplicationName\":\"test\"}", &signature).is_ok());
+        assert!(matches!(verify(&public, b"{\"ApplicationName\":\"tampered\"}", &signature), Err(SignatureError::Mismatch)));
+        assert!(matches!(verify(&public, b"", "not a signature"), Err(SignatureError::Malformed)));
+    }
+}
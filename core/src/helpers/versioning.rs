@@ -1,21 +1,290 @@
+use std::{cmp::Ordering, fmt, str::FromStr};
 
-pub fn version_compare(v1: &str, v2: &str) -> std::cmp::Ordering {
-    let v1_parts: Vec<u32> = v1
-        .split('.')
-        .map(|s| s.parse::<u32>().unwrap())
-        .collect();
+use crate::*;
 
-    let v2_parts: Vec<u32> = v2
-        .split('.') 
-        .map(|s| s.parse::<u32>().unwrap()) 
-        .collect();
+use rust_i18n::error::*;
+use convert_case::*;
 
-    for (v1_part, v2_part) in v1_parts.iter().zip(v2_parts.iter()) {
-        match v1_part.cmp(&v2_part) {
-            std::cmp::Ordering::Equal => {}
-            other => return other,
+#[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
+pub enum VersionError {
+    #[error("empty")]
+    Empty,
+
+    #[error("invalid-release")]
+    InvalidRelease(String),
+
+    #[error("invalid-identifier")]
+    InvalidIdentifier(String),
+
+    #[error("invalid-constraint")]
+    InvalidConstraint(String),
+}
+
+/// A dot separated pre-release identifier. Numeric identifiers have lower precedence than alphanumeric ones
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    Numeric(u64),
+    Alpha(String),
+}
+
+/// A package version following SemVer 2.0 precedence rules, such as '1.2.0-beta.1+build5'.
+///
+/// Versions that are not valid SemVer, like date style '2024.01.15' or 'v1.2', are accepted in lenient mode.
+/// There, any number of release components is allowed and missing ones are treated as zero.
+#[derive(Clone, Debug)]
+pub struct Version {
+    release: Vec<u64>,
+    pre: Vec<Identifier>,
+    build: Vec<String>,
+}
+
+impl Version {
+    /// Parses a SemVer version, falling back to lenient mode if it's not valid SemVer
+    pub fn parse(text: &str) -> Result<Self, VersionError> {
+        Self::parse_strict(text).or_else(|_| Self::parse_lenient(text))
+    }
+
+    /// Parses a version that strictly follows SemVer 2.0, 'MAJOR.MINOR.PATCH[-PRE][+BUILD]'
+    pub fn parse_strict(text: &str) -> Result<Self, VersionError> {
+        Self::parse_with(text, true)
+    }
+
+    /// Parses a version with any number of numeric release components. Leading zeros and a 'v' prefix are allowed
+    pub fn parse_lenient(text: &str) -> Result<Self, VersionError> {
+        Self::parse_with(text, false)
+    }
+
+    /// Gets whether the version is a pre-release
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    fn parse_with(text: &str, strict: bool) -> Result<Self, VersionError> {
+        let trimmed = text.trim();
+        let trimmed = match strict {
+            true => trimmed,
+            false => trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed),
+        };
+
+        if trimmed.is_empty() {
+            return Err(VersionError::Empty);
+        }
+
+        let (rest, build) = match trimmed.split_once('+') {
+            Some((rest, build)) => (rest, Some(build)),
+            None => (trimmed, None),
+        };
+
+        let (release, pre) = match rest.split_once('-') {
+            Some((release, pre)) => (release, Some(pre)),
+            None => (rest, None),
+        };
+
+        let release = release.split('.')
+            .map(|part| numeric(part, strict).ok_or_else(|| VersionError::InvalidRelease(text.to_owned())))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if strict && release.len() != 3 {
+            return Err(VersionError::InvalidRelease(text.to_owned()));
+        }
+
+        let pre = pre.map(|pre| pre.split('.')
+            .map(|part| match numeric(part, strict) {
+                Some(number) => Ok(Identifier::Numeric(number)),
+                None if is_identifier(part) && !part.bytes().all(|b| b.is_ascii_digit()) => Ok(Identifier::Alpha(part.to_owned())),
+                None => Err(VersionError::InvalidIdentifier(text.to_owned())),
+            })
+            .collect::<Result<Vec<_>, _>>())
+            .transpose()?
+            .unwrap_or_default();
+
+        let build = build.map(|build| build.split('.')
+            .map(|part| match is_identifier(part) {
+                true => Ok(part.to_owned()),
+                false => Err(VersionError::InvalidIdentifier(text.to_owned())),
+            })
+            .collect::<Result<Vec<_>, _>>())
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Version { release, pre, build })
+    }
+}
+
+fn numeric(part: &str, strict: bool) -> Option<u64> {
+    if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) || (strict && part.len() > 1 && part.starts_with('0')) {
+        return None;
+    }
+
+    part.parse::<u64>().ok()
+}
+
+fn is_identifier(part: &str) -> bool {
+    !part.is_empty() && part.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // trailing zeros don't change the release, '1.2' equals '1.2.0'
+        let len = self.release.len().max(other.release.len());
+        for i in 0..len {
+            let ordering = self.release.get(i).unwrap_or(&0).cmp(other.release.get(i).unwrap_or(&0));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+
+        // a pre-release has lower precedence than the release itself. build metadata is ignored
+        match (self.pre.is_empty(), other.pre.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => self.pre.cmp(&other.pre),
+        }
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+impl FromStr for Version {
+    type Err = VersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.release.iter().map(|n| n.to_string()).collect::<Vec<_>>().join("."))?;
+
+        if !self.pre.is_empty() {
+            let pre = self.pre.iter()
+                .map(|i| match i {
+                    Identifier::Numeric(n) => n.to_string(),
+                    Identifier::Alpha(s) => s.clone(),
+                })
+                .collect::<Vec<_>>();
+
+            write!(f, "-{}", pre.join("."))?;
+        }
+
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build.join("."))?;
         }
+
+        Ok(())
     }
+}
+
+/// Compares two version strings, see 'Version' for the precedence rules
+pub fn version_compare(v1: &str, v2: &str) -> Result<Ordering, VersionError> {
+    Ok(Version::parse(v1)?.cmp(&Version::parse(v2)?))
+}
 
-    v1_parts.len().cmp(&v2_parts.len()) 
-}
\ No newline at end of file
+/// Comparison operator of a 'VersionConstraint'
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConstraintOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+/// A single version bound such as '>= 2.1'
+#[derive(Clone, Debug, PartialEq)]
+pub struct VersionConstraint {
+    pub op: ConstraintOp,
+    pub version: Version,
+}
+
+impl VersionConstraint {
+    /// Parses a bound such as '>= 2.1' or '< 3'. A bare version is treated as '='
+    pub fn parse(text: &str) -> Result<Self, VersionError> {
+        let text = text.trim();
+        let (op, rest) = [(">=", ConstraintOp::Ge), ("<=", ConstraintOp::Le), ("!=", ConstraintOp::Ne), ("==", ConstraintOp::Eq), ("=", ConstraintOp::Eq), (">", ConstraintOp::Gt), ("<", ConstraintOp::Lt)]
+            .iter()
+            .find_map(|(token, op)| text.strip_prefix(token).map(|rest| (*op, rest)))
+            .unwrap_or((ConstraintOp::Eq, text));
+
+        let version = rest.trim();
+        if version.is_empty() || version.contains(char::is_whitespace) {
+            return Err(VersionError::InvalidConstraint(text.to_owned()));
+        }
+
+        Ok(VersionConstraint { op, version: Version::parse(version)? })
+    }
+
+    /// Checks whether 'version' satisfies this bound
+    pub fn matches(&self, version: &Version) -> bool {
+        let ordering = version.cmp(&self.version);
+        match self.op {
+            ConstraintOp::Eq => ordering == Ordering::Equal,
+            ConstraintOp::Ne => ordering != Ordering::Equal,
+            ConstraintOp::Gt => ordering == Ordering::Greater,
+            ConstraintOp::Ge => ordering != Ordering::Less,
+            ConstraintOp::Lt => ordering == Ordering::Less,
+            ConstraintOp::Le => ordering != Ordering::Greater,
+        }
+    }
+}
+
+impl fmt::Display for VersionConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            ConstraintOp::Eq => "=",
+            ConstraintOp::Ne => "!=",
+            ConstraintOp::Gt => ">",
+            ConstraintOp::Ge => ">=",
+            ConstraintOp::Lt => "<",
+            ConstraintOp::Le => "<=",
+        };
+
+        write!(f, "{} {}", op, self.version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_semver_precedence() {
+        let ordered = ["1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-alpha.beta", "1.0.0-beta", "1.0.0-beta.2", "1.0.0-beta.11", "1.0.0-rc.1", "1.0.0", "1.2.0-beta.1", "1.2.0"];
+        for pair in ordered.windows(2) {
+            assert_eq!(version_compare(pair[0], pair[1]).unwrap(), Ordering::Less, "{} < {}", pair[0], pair[1]);
+        }
+
+        assert_eq!(version_compare("1.0.0+build5", "1.0.0+build6").unwrap(), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_lenient() {
+        assert!(Version::parse_strict("2024.01.15").is_err());
+        assert_eq!(version_compare("2024.01.15", "2024.1.3+build5").unwrap(), Ordering::Greater);
+        assert_eq!(version_compare("v1.2", "1.2.0").unwrap(), Ordering::Equal);
+        assert_eq!(version_compare("1.2.3.4", "1.2.3").unwrap(), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(matches!(Version::parse(""), Err(VersionError::Empty)));
+        assert!(matches!(Version::parse("1.x.0"), Err(VersionError::InvalidRelease(_))));
+        assert!(matches!(Version::parse("1.0.0-beta..1"), Err(VersionError::InvalidIdentifier(_))));
+        assert!(matches!(VersionConstraint::parse(">="), Err(VersionError::InvalidConstraint(_))));
+    }
+}
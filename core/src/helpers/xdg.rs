@@ -0,0 +1,73 @@
+use std::path::{Path, PathBuf};
+
+use directories::BaseDirs;
+
+/// Gets per-user directories by formatter name, such as 'Data' for '~/.local/share'.
+/// Follows the XDG base directory spec on Linux, platform equivalents are used elsewhere
+pub fn user_directories() -> Vec<(&'static str, PathBuf)> {
+    let Some(dirs) = BaseDirs::new() else {
+        return Vec::new();
+    };
+
+    let data = dirs.data_dir().to_path_buf();
+    vec![
+        ("Data", data.clone()),
+        ("Config", dirs.config_dir().to_path_buf()),
+        ("Cache", dirs.cache_dir().to_path_buf()),
+        ("State", dirs.state_dir().unwrap_or(&data).to_path_buf()),
+        ("Bin", dirs.executable_dir().map(Path::to_path_buf).unwrap_or_else(|| dirs.home_dir().join(".local").join("bin"))),
+        ("Applications", data.join("applications")),
+        ("Icons", data.join("icons")),
+    ]
+}
+
+/// Gets system-wide directories by formatter name, such as 'Opt' for '/opt'
+#[cfg(not(target_os = "windows"))]
+pub fn system_directories() -> Vec<(&'static str, PathBuf)> {
+    let data = first_path(std::env::var("XDG_DATA_DIRS").ok().as_deref(), "/usr/local/share");
+    vec![
+        ("Data", data.clone()),
+        ("Config", first_path(std::env::var("XDG_CONFIG_DIRS").ok().as_deref(), "/etc/xdg")),
+        ("Bin", PathBuf::from("/usr/local/bin")),
+        ("Applications", data.join("applications")),
+        ("Icons", data.join("icons")),
+        ("Opt", PathBuf::from("/opt")),
+        ("Local", PathBuf::from("/usr/local")),
+    ]
+}
+
+/// Gets system-wide directories by formatter name, such as 'Opt' for 'C:\Program Files'
+#[cfg(target_os = "windows")]
+pub fn system_directories() -> Vec<(&'static str, PathBuf)> {
+    let data = PathBuf::from(std::env::var("ProgramData").unwrap_or(r"C:\ProgramData".into()));
+    let program_files = PathBuf::from(std::env::var("ProgramFiles").unwrap_or(r"C:\Program Files".into()));
+    vec![
+        ("Data", data.clone()),
+        ("Config", data.clone()),
+        ("Bin", program_files.clone()),
+        ("Applications", data.join("Microsoft").join("Windows").join("Start Menu").join("Programs")),
+        ("Icons", data.clone()),
+        ("Opt", program_files.clone()),
+        ("Local", program_files),
+    ]
+}
+
+/// Gets the first entry of a colon separated XDG path list, 'default' if it's unset or empty
+fn first_path(list: Option<&str>, default: &str) -> PathBuf {
+    list.and_then(|list| list.split(':').find(|p| !p.is_empty()))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(default))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_path() {
+        assert_eq!(first_path(Some("/usr/share:/usr/local/share"), "/default"), PathBuf::from("/usr/share"));
+        assert_eq!(first_path(Some(":/usr/share"), "/default"), PathBuf::from("/usr/share"));
+        assert_eq!(first_path(Some(""), "/default"), PathBuf::from("/default"));
+        assert_eq!(first_path(None, "/default"), PathBuf::from("/default"));
+    }
+}
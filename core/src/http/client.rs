@@ -1,6 +1,6 @@
 
 use futures::StreamExt;
-use std::{fs::File, io::{Seek, SeekFrom, Write}, time::Duration};
+use std::{fs::File, io::{Seek, SeekFrom, Write}, path::PathBuf, time::Duration};
 use bytes::Bytes;
 use once_cell::sync::Lazy;
 
@@ -8,6 +8,8 @@ use rust_i18n::error::*;
 use convert_case::*;
 use crate::*;
 
+use super::mirror::MirrorList;
+
 #[derive(thiserror::Error, rust_i18n::AsDetails, strum::AsRefStr, Debug)]
 pub enum HttpStreamError {
     #[error("network")]
@@ -23,10 +25,22 @@ pub enum HttpStreamError {
     PullToFile(#[from] std::io::Error),
 
     #[error("pull-to-string-utf8")]
-    PullToString(#[from] std::string::FromUtf8Error)
+    PullToString(#[from] std::string::FromUtf8Error),
+
+    #[error("local.{}", local_error_key(.0))]
+    Local(std::io::Error),
+}
+
+/// Gets the message key of a local repository read failure, kinds without a message of their own share 'other-error'
+fn local_error_key(err: &std::io::Error) -> &'static str {
+    match err.kind() {
+        std::io::ErrorKind::PermissionDenied => "permission-denied",
+        _ => "other-error",
+    }
 }
 
 const MAX_RETRIES: u32 = 4;
+const LOCAL_CHUNK_SIZE: usize = 64 * 1024;
 const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
 const READ_TIMEOUT: Duration = Duration::from_secs(30);
@@ -50,10 +64,15 @@ fn is_retryable(err: &HttpStreamError) -> bool {
             matches!(code, 408 | 425 | 429 | 500 | 502 | 503 | 504)
         }
         HttpStreamError::ContentLength => true,
-        HttpStreamError::PullToFile(_) | HttpStreamError::PullToString(_) => false,
+        HttpStreamError::PullToFile(_) | HttpStreamError::PullToString(_) | HttpStreamError::Local(_) => false,
     }
 }
 
+/// Whether the request should be sent to the next mirror. Local i/o and encoding failures won't be solved by another mirror
+fn is_failover(err: &HttpStreamError) -> bool {
+    !matches!(err, HttpStreamError::PullToFile(_) | HttpStreamError::PullToString(_))
+}
+
 fn backoff_delay(attempt: u32) -> Duration {
     RETRY_BASE_DELAY * 2u32.pow(attempt.saturating_sub(1))
 }
@@ -67,6 +86,62 @@ pub async fn test() -> Result<String, HttpStreamError> {
     Ok(resp)
 }
 
+/// Gets the path 'url' refers to if it's a 'file://' url or a plain filesystem path
+pub fn local_path(url: &str) -> Option<PathBuf> {
+    if url.starts_with("file://") {
+        return reqwest::Url::parse(url).ok()?.to_file_path().ok();
+    }
+
+    match reqwest::Url::parse(url) {
+        // single letter schemes are windows drive letters, such as 'C:\repository'
+        Ok(parsed) if parsed.scheme().len() > 1 => None,
+        _ => Some(PathBuf::from(url)),
+    }
+}
+
+/// Streams the file at 'path' in chunks, reporting progress the same way as remote downloads
+async fn read_local<F, P>(
+    path: PathBuf,
+    mut progress_callback: F,
+    mut process_chunk: P,
+) -> Result<(), HttpStreamError>
+where
+    F: FnMut(f32),
+    P: FnMut(Bytes) -> Result<(), HttpStreamError>,
+{
+    use tokio::io::AsyncReadExt;
+
+    // missing files are reported like missing remote ones, callers tell them apart by status code
+    let local_err = |err: std::io::Error| match err.kind() {
+        std::io::ErrorKind::NotFound => HttpStreamError::StatusCode(404),
+        _ => HttpStreamError::Local(err),
+    };
+
+    let mut file = tokio::fs::File::open(&path).await.map_err(local_err)?;
+    let total_size = file.metadata().await.map_err(local_err)?.len();
+
+    let mut read: u64 = 0;
+    let mut buffer = vec![0; LOCAL_CHUNK_SIZE];
+
+    loop {
+        let size = file.read(&mut buffer).await.map_err(local_err)?;
+        if size == 0 {
+            break;
+        }
+
+        process_chunk(Bytes::copy_from_slice(&buffer[..size]))?;
+
+        read += size as u64;
+        if total_size > 0 {
+            let progress = (read as f32 / total_size as f32) * 100.0;
+            progress_callback(progress.clamp(0.0, 100.0));
+        }
+    }
+
+    Ok(())
+}
+
+/// Streams 'url' in chunks. Besides http(s), 'file://' urls and plain filesystem paths are read from disk
 pub async fn download<F, P>(
     url: &str,
     mut progress_callback: F,
@@ -76,6 +151,10 @@ where
     F: FnMut(f32),
     P: FnMut(Bytes) -> Result<(), HttpStreamError>,
 {
+    if let Some(path) = local_path(url) {
+        return read_local(path, progress_callback, process_chunk).await;
+    }
+
     let response = CLIENT.get(url)
         .send()
         .await?;
@@ -165,3 +244,91 @@ where
         }
     }
 }
+
+/// Downloads `url` into `file`, failing over to the next mirror once retries are used up on one.
+/// Files of the bundled repository, if there is one, are copied from it instead.
+pub async fn get_file_mirrored<F>(mirrors: &MirrorList, url: &str, file: &mut File, mut progress_callback: F) -> Result<(), HttpStreamError>
+where
+    F: FnMut(f32),
+{
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    if mirrors.read_bundled(url, file) {
+        file.flush().map_err(HttpStreamError::PullToFile)?;
+        progress_callback(100.0);
+        return Ok(());
+    }
+
+    let mut candidates = mirrors.candidates(url).into_iter().peekable();
+    loop {
+        let (index, url) = candidates.next().expect("mirror list yields at least one candidate");
+        match get_file(&url, file, &mut progress_callback).await {
+            Ok(()) => {
+                if let Some(index) = index {
+                    mirrors.remember(index);
+                }
+                return Ok(());
+            }
+            Err(err) if is_failover(&err) && candidates.peek().is_some() => {
+                log::warn!("Download of '{}' failed: {}. Trying the next mirror.", url, err);
+                progress_callback(0.0);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// Downloads `url` as a UTF-8 string, failing over to the next mirror once retries are used up on one.
+/// Files of the bundled repository, if there is one, are read from it instead.
+pub async fn get_text_mirrored<F>(mirrors: &MirrorList, url: &str, mut progress_callback: F) -> Result<String, HttpStreamError>
+where
+    F: FnMut(f32),
+{
+    let mut bundled = Vec::new();
+    if mirrors.read_bundled(url, &mut bundled) {
+        return String::from_utf8(bundled).map_err(HttpStreamError::PullToString);
+    }
+
+    let mut candidates = mirrors.candidates(url).into_iter().peekable();
+    loop {
+        let (index, url) = candidates.next().expect("mirror list yields at least one candidate");
+        match get_text(&url, &mut progress_callback).await {
+            Ok(text) => {
+                if let Some(index) = index {
+                    mirrors.remember(index);
+                }
+                return Ok(text);
+            }
+            Err(err) if is_failover(&err) && candidates.peek().is_some() => {
+                log::warn!("Fetch of '{}' failed: {}. Trying the next mirror.", url, err);
+                progress_callback(0.0);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_path() {
+        assert_eq!(local_path("https://example.com/repository/"), None);
+        assert_eq!(local_path("./repository/repository.json"), Some(PathBuf::from("./repository/repository.json")));
+
+        #[cfg(unix)]
+        assert_eq!(local_path("file:///media/usb/my%20app/repository.json"), Some(PathBuf::from("/media/usb/my app/repository.json")));
+        #[cfg(windows)]
+        assert_eq!(local_path(r"D:\repository\repository.json"), Some(PathBuf::from(r"D:\repository\repository.json")));
+    }
+
+    #[test]
+    fn test_local_error_key() {
+        let local = |kind| HttpStreamError::Local(std::io::Error::from(kind)).to_string();
+
+        assert_eq!(local(std::io::ErrorKind::PermissionDenied), "local.permission-denied");
+        assert_eq!(local(std::io::ErrorKind::UnexpectedEof), "local.other-error");
+        assert_eq!(local(std::io::ErrorKind::Other), "local.other-error");
+    }
+}
@@ -0,0 +1,150 @@
+use std::{io::Write, sync::Arc};
+
+use parking_lot::Mutex;
+
+use crate::archiving::bundle::Bundle;
+
+/// Base urls a repository is served from, in the order they are tried.
+/// The mirror that served the last successful request is preferred for the following ones.
+#[derive(Debug, Default)]
+pub struct MirrorList {
+    bases: Vec<String>,
+    preferred: Mutex<Option<usize>>,
+    /// Repository bundled into the setup binary, its files are served before any of the mirrors is tried
+    bundle: Option<Arc<Bundle>>,
+}
+
+impl MirrorList {
+    pub fn new(bases: Vec<String>) -> Self {
+        Self { bases, preferred: Mutex::new(None), bundle: None }
+    }
+
+    /// Creates a list where files of 'bundle' are served first, the mirrors are only fallbacks for files missing in it
+    pub fn with_bundle(bundle: Arc<Bundle>, bases: Vec<String>) -> Self {
+        Self { bases, preferred: Mutex::new(None), bundle: Some(bundle) }
+    }
+
+    /// Copies the bundled file 'url' refers to into 'writer'. Returns false if there is no bundle, the url is not
+    /// under any of the mirrors or the file is not bundled
+    pub fn read_bundled<W: ?Sized + Write>(&self, url: &str, writer: &mut W) -> bool {
+        let Some(bundle) = &self.bundle else {
+            return false;
+        };
+
+        let Some(path) = self.bases.iter().find_map(|base| url.strip_prefix(base.as_str())) else {
+            return false;
+        };
+
+        match bundle.read_to(path, writer) {
+            Ok(found) => found,
+            Err(err) => {
+                log::warn!("Failed to read '{}' from the bundled repository, trying the mirrors. {:?}", path, err);
+                false
+            }
+        }
+    }
+
+    /// Gets the base url requests are currently sent to
+    pub fn get_preferred(&self) -> Option<&str> {
+        let index = self.preferred.lock().unwrap_or(0);
+        self.bases.get(index).map(|base| base.as_str())
+    }
+
+    /// Remembers the mirror at 'index' for the rest of the workload
+    pub fn remember(&self, index: usize) {
+        let mut preferred = self.preferred.lock();
+        if *preferred != Some(index) {
+            log::info!("Using mirror '{}' for the following requests.", self.bases[index]);
+            *preferred = Some(index);
+        }
+    }
+
+    /// Gets the urls to try for 'url', one per mirror, starting with the preferred one.
+    /// Urls that are not under any of the mirrors are returned as is, without an index
+    pub fn candidates(&self, url: &str) -> Vec<(Option<usize>, String)> {
+        let Some(path) = self.bases.iter().find_map(|base| url.strip_prefix(base.as_str())) else {
+            return vec![(None, url.to_owned())];
+        };
+
+        let preferred = *self.preferred.lock();
+        let mut order = (0..self.bases.len()).collect::<Vec<_>>();
+        if let Some(index) = preferred {
+            order.retain(|i| *i != index);
+            order.insert(0, index);
+        }
+
+        order.into_iter()
+            .map(|i| (Some(i), format!("{}{}", self.bases[i], path)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{definitions::product::{Mirror, Product}, http::client};
+
+    fn base(dir: &std::path::Path) -> String {
+        format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR)
+    }
+
+    #[test]
+    fn test_weight_order() {
+        let mut product = Product::new("app", "App", "publisher", "https://example.com", "https://example.com/repository/", "", "");
+        product.mirrors = vec![
+            Mirror { url: "https://a.example.com/".to_owned(), weight: 1 },
+            Mirror { url: "https://b.example.com/".to_owned(), weight: 5 },
+            Mirror { url: "https://c.example.com/".to_owned(), weight: 1 },
+        ];
+
+        let urls = product.get_mirror_urls();
+        assert_eq!(urls, ["https://example.com/repository/", "https://b.example.com/", "https://a.example.com/", "https://c.example.com/"]);
+
+        let mirrors = MirrorList::new(urls);
+        let candidates = mirrors.candidates("https://example.com/repository/repository.json");
+        assert_eq!(candidates, [
+            (Some(0), "https://example.com/repository/repository.json".to_owned()),
+            (Some(1), "https://b.example.com/repository.json".to_owned()),
+            (Some(2), "https://a.example.com/repository.json".to_owned()),
+            (Some(3), "https://c.example.com/repository.json".to_owned()),
+        ]);
+
+        assert_eq!(mirrors.candidates("https://elsewhere.com/file"), [(None, "https://elsewhere.com/file".to_owned())]);
+    }
+
+    #[test]
+    fn test_remember() {
+        let mirrors = MirrorList::new(vec!["https://a/".to_owned(), "https://b/".to_owned(), "https://c/".to_owned()]);
+        assert_eq!(mirrors.get_preferred(), Some("https://a/"));
+
+        mirrors.remember(2);
+        assert_eq!(mirrors.get_preferred(), Some("https://c/"));
+
+        let order = mirrors.candidates("https://a/file").into_iter().map(|(index, _)| index).collect::<Vec<_>>();
+        assert_eq!(order, [Some(2), Some(0), Some(1)]);
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        std::fs::write(second.path().join("repository.json"), "{}").unwrap();
+
+        let mirrors = MirrorList::new(vec![base(first.path()), base(second.path())]);
+        let url = format!("{}repository.json", base(first.path()));
+
+        // missing on the first mirror, served by the second one which is kept for the following requests
+        let text = client::get_text_mirrored(&mirrors, &url, |_| ()).await.unwrap();
+        assert_eq!(text, "{}");
+        assert_eq!(mirrors.get_preferred(), Some(base(second.path()).as_str()));
+
+        let mut file = tempfile::tempfile().unwrap();
+        client::get_file_mirrored(&mirrors, &url, &mut file, |_| ()).await.unwrap();
+        assert_eq!(mirrors.candidates(&url)[0].0, Some(1));
+
+        // the last mirror's error is returned once every mirror failed
+        let missing = format!("{}missing.json", base(first.path()));
+        let err = client::get_text_mirrored(&mirrors, &missing, |_| ()).await.unwrap_err();
+        assert!(matches!(err, client::HttpStreamError::StatusCode(404)));
+    }
+}
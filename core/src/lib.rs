@@ -10,6 +10,7 @@ pub mod helpers;
 pub mod extensions;
 pub mod factory;
 pub mod scripting;
+pub mod lint;
 
 pub(crate) mod target;
 
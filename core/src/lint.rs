@@ -0,0 +1,301 @@
+use std::{collections::HashMap, fmt, path::{Path, PathBuf}};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{definitions::{package::PackageDefinition, product::Product, requirement::PackageRequirement}, helpers::{formatter::TemplateFormat, serializer, versioning::Version}, http::client, scripting::builder};
+
+/// A single problem found in a configuration file, pointing at the field it's about
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintProblem {
+    pub file: PathBuf,
+
+    /// Path of the offending field, such as 'Mirrors[1].Url'. Empty for problems about the whole file
+    pub field: String,
+    pub message: String,
+}
+
+impl LintProblem {
+    fn new<P: AsRef<Path>>(file: P, field: &str, message: impl Into<String>) -> Self {
+        LintProblem { file: file.as_ref().to_path_buf(), field: field.to_owned(), message: message.into() }
+    }
+}
+
+impl fmt::Display for LintProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.field.as_str() {
+            "" => write!(f, "{}: {}", self.file.display(), self.message),
+            field => write!(f, "{}: {}: {}", self.file.display(), field, self.message),
+        }
+    }
+}
+
+/// Checks the repogen layout, 'product.json' and scripts of 'config_dir' along with
+/// 'meta/package.json' and scripts of every package folder in 'source_dir'
+pub fn lint_source<P: AsRef<Path>>(config_dir: P, source_dir: P) -> Vec<LintProblem> {
+    let config_dir = config_dir.as_ref();
+    let source_dir = source_dir.as_ref();
+
+    let product_path = config_dir.join("product.json");
+    let (product, mut problems) = lint_product(&product_path);
+
+    let mut package_dirs = match std::fs::read_dir(source_dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()).collect::<Vec<_>>(),
+        Err(err) => {
+            problems.push(LintProblem::new(source_dir, "", format!("source folder can't be read, {}", err)));
+            return problems;
+        }
+    };
+    package_dirs.sort();
+
+    // package names need to be unique per release channel
+    let mut seen = HashMap::<(String, String), PathBuf>::new();
+    for package_dir in package_dirs {
+        let definition_path = package_dir.join("meta").join("package.json");
+        let (definition, package_problems) = lint_package_definition(&definition_path, product.as_ref());
+        problems.extend(package_problems);
+
+        let Some(definition) = definition else {
+            continue;
+        };
+
+        match seen.get(&(definition.name.clone(), definition.channel.clone())) {
+            Some(other) => problems.push(LintProblem::new(&definition_path, "Name", format!("'{}' is already defined by {}", definition.name, other.display()))),
+            None => _ = seen.insert((definition.name.clone(), definition.channel.clone()), definition_path),
+        }
+    }
+
+    problems
+}
+
+/// Checks a 'product.json' template. The product is returned if the file could be read at all
+pub fn lint_product<P: AsRef<Path>>(path: P) -> (Option<Product>, Vec<LintProblem>) {
+    let path = path.as_ref();
+    let mut problems = vec![];
+
+    let product: Product = match serializer::from_json_file(path) {
+        Ok(product) => product,
+        Err(err) => return (None, vec![LintProblem::new(path, "", format!("can't be read, {:?}", err))]),
+    };
+
+    for (field, value) in [("Name", &product.name), ("Title", &product.title), ("Publisher", &product.publisher), ("Repository", &product.repository), ("TargetDirectory", &product.target_directory)] {
+        if value.trim().is_empty() {
+            problems.push(LintProblem::new(path, field, "is required"));
+        }
+    }
+
+    if !product.repository.is_empty() {
+        check_base_url(path, "Repository", &product.repository, &mut problems);
+    }
+
+    for (i, mirror) in product.mirrors.iter().enumerate() {
+        check_base_url(path, &format!("Mirrors[{}].Url", i), &mirror.url, &mut problems);
+    }
+
+    check_templates(path, &product, &product.create_formatter(), &mut problems);
+
+    if !product.script.is_empty() {
+        let script_dir = path.parent().unwrap_or(Path::new(""));
+        check_script(path, "Script", &script_dir.join(&product.script), &mut problems);
+    }
+
+    (Some(product), problems)
+}
+
+/// Checks a 'meta/package.json' definition, scripts are looked up next to it. Install paths are
+/// expanded through the formatter of 'product' when given. The definition is returned if the file could be read at all
+pub fn lint_package_definition<P: AsRef<Path>>(path: P, product: Option<&Product>) -> (Option<PackageDefinition>, Vec<LintProblem>) {
+    let path = path.as_ref();
+    let mut problems = vec![];
+
+    let definition = match PackageDefinition::from_file(path) {
+        Ok(definition) => definition,
+        Err(err) => return (None, vec![LintProblem::new(path, "", format!("can't be read, {:?}", err))]),
+    };
+
+    for (field, value) in [("Name", &definition.name), ("DisplayName", &definition.display_name), ("Version", &definition.version), ("ReleaseDate", &definition.release_date)] {
+        if value.trim().is_empty() {
+            problems.push(LintProblem::new(path, field, "is required"));
+        }
+    }
+
+    if !definition.version.is_empty() {
+        if let Err(err) = Version::parse(&definition.version) {
+            problems.push(LintProblem::new(path, "Version", format!("'{}' is not a valid version, {:?}", definition.version, err)));
+        }
+    }
+
+    for (field, requirements) in [("Dependencies", &definition.dependencies), ("Conflicts", &definition.conflicts), ("Replaces", &definition.replaces)] {
+        for (i, requirement) in requirements.iter().enumerate() {
+            if let Err(err) = PackageRequirement::parse(requirement) {
+                problems.push(LintProblem::new(path, &format!("{}[{}]", field, i), format!("'{}' is not a valid requirement, {:?}", requirement, err)));
+            }
+        }
+    }
+
+    // globs are matched against paths relative to the package directory
+    for (i, pattern) in definition.config_files.iter().enumerate() {
+        if pattern.trim().is_empty() || pattern.starts_with('/') || pattern.split('/').any(|segment| segment == "..") {
+            problems.push(LintProblem::new(path, &format!("ConfigFiles[{}]", i), format!("'{}' is not relative to the package directory", pattern)));
+        }
+    }
+
+    if !definition.script.is_empty() {
+        let script_dir = path.parent().unwrap_or(Path::new(""));
+        check_script(path, "Script", &script_dir.join(&definition.script), &mut problems);
+    }
+
+    if let Some(product) = product {
+        let formatter = product.create_formatter();
+        if let Err(err) = formatter.format_strict(&definition.install_path) {
+            problems.push(LintProblem::new(path, "InstallPath", format!("template doesn't resolve, {:?}", err)));
+        }
+    }
+
+    (Some(definition), problems)
+}
+
+/// Checks that 'url' is something the http client can serve a repository from. Package and
+/// metadata uris are built by appending to it, so it has to end with a separator
+fn check_base_url(file: &Path, field: &str, url: &str, problems: &mut Vec<LintProblem>) {
+    let is_local = client::local_path(url).is_some();
+    if !is_local {
+        match reqwest::Url::parse(url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => (),
+            Ok(parsed) => problems.push(LintProblem::new(file, field, format!("'{}' scheme is not supported", parsed.scheme()))),
+            Err(err) => problems.push(LintProblem::new(file, field, format!("'{}' is not a valid url, {}", url, err))),
+        }
+    }
+
+    let has_separator = url.ends_with('/') || (is_local && url.ends_with(std::path::MAIN_SEPARATOR));
+    if !has_separator {
+        problems.push(LintProblem::new(file, field, format!("'{}' must end with a trailing slash", url)));
+    }
+}
+
+/// Checks that every string field of 'value' expands through 'formatter' without unresolved placeholders
+fn check_templates<T: Serialize>(file: &Path, value: &T, formatter: &TemplateFormat, problems: &mut Vec<LintProblem>) {
+    let Ok(value) = serde_json::to_value(value) else {
+        return;
+    };
+
+    let mut strings = vec![];
+    collect_strings(&value, String::new(), &mut strings);
+
+    for (field, text) in strings {
+        if let Err(err) = formatter.format_strict(text) {
+            problems.push(LintProblem::new(file, &field, format!("template doesn't resolve, {:?}", err)));
+        }
+    }
+}
+
+/// Collects string leaves of a json value along with their field paths
+fn collect_strings<'a>(value: &'a Value, field: String, strings: &mut Vec<(String, &'a str)>) {
+    match value {
+        Value::String(text) => strings.push((field, text)),
+        Value::Array(items) => items.iter().enumerate()
+            .for_each(|(i, item)| collect_strings(item, format!("{}[{}]", field, i), strings)),
+        Value::Object(map) => map.iter()
+            .for_each(|(key, item)| collect_strings(item, if field.is_empty() { key.clone() } else { format!("{}.{}", field, key) }, strings)),
+        _ => (),
+    }
+}
+
+/// Checks that the script at 'script_path' exists and compiles
+fn check_script(file: &Path, field: &str, script_path: &Path, problems: &mut Vec<LintProblem>) {
+    let src = match std::fs::read_to_string(script_path) {
+        Ok(src) => src,
+        Err(err) => {
+            problems.push(LintProblem::new(file, field, format!("{} can't be read, {}", script_path.display(), err)));
+            return;
+        }
+    };
+
+    if let Err(err) = builder::check_syntax(&src) {
+        problems.push(LintProblem::new(file, field, format!("{} doesn't compile, {:?}", script_path.display(), err)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_base_url() {
+        let file = Path::new("product.json");
+        let mut problems = vec![];
+
+        check_base_url(file, "Repository", "https://example.com/repository/", &mut problems);
+        check_base_url(file, "Repository", "file:///srv/repository/", &mut problems);
+        assert!(problems.is_empty());
+
+        check_base_url(file, "Repository", "https://example.com/repository", &mut problems);
+        check_base_url(file, "Mirrors[0].Url", "ftp://example.com/repository/", &mut problems);
+        assert_eq!(problems.iter().map(|p| p.field.as_str()).collect::<Vec<_>>(), ["Repository", "Mirrors[0].Url"]);
+    }
+
+    fn write_package(source_dir: &Path, folder: &str, definition: serde_json::Value) {
+        let meta_dir = source_dir.join(folder).join("meta");
+        std::fs::create_dir_all(&meta_dir).unwrap();
+        std::fs::write(meta_dir.join("package.json"), definition.to_string()).unwrap();
+    }
+
+    fn definition(name: &str) -> serde_json::Value {
+        serde_json::json!({ "Name": name, "DisplayName": name, "Version": "1.0.0", "ReleaseDate": "2024-01-01", "Default": true, "Script": "" })
+    }
+
+    #[test]
+    fn test_lint_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_dir = dir.path().join("config");
+        let source_dir = dir.path().join("source");
+        std::fs::create_dir_all(&config_dir).unwrap();
+
+        let product = serde_json::json!({
+            "Name": "app", "Title": "@{App.Name}", "Publisher": "publisher", "ProductUrl": "https://example.com", "Repository": "https://example.com/repository/",
+            "Script": "", "TargetDirectory": "@{Directories.User.Home}/app", "Mirrors": [{ "Url": "https://mirror.example.com/" }, { "Url": "https://example.com/@{Unknown}/" }]
+        });
+        std::fs::write(config_dir.join("product.json"), product.to_string()).unwrap();
+
+        let mut missing = definition("a");
+        missing["DisplayName"] = "".into();
+        write_package(&source_dir, "a", missing);
+
+        write_package(&source_dir, "b", definition("a"));
+
+        let mut beta = definition("a");
+        beta["Channel"] = "beta".into();
+        write_package(&source_dir, "b-beta", beta);
+
+        let mut script = definition("c");
+        script["Script"] = "script.js".into();
+        write_package(&source_dir, "c", script);
+        std::fs::write(source_dir.join("c/meta/script.js"), "let x = ;").unwrap();
+
+        let mut install_path = definition("d");
+        install_path["InstallPath"] = "@{App.TargetDirectory}/@{App.Unknown}".into();
+        write_package(&source_dir, "d", install_path);
+
+        let problems = lint_source(&config_dir, &source_dir);
+        let found = problems.iter().map(|p| (p.file.strip_prefix(dir.path()).unwrap().to_path_buf(), p.field.as_str())).collect::<Vec<_>>();
+        let package = |folder: &str| Path::new("source").join(folder).join("meta").join("package.json");
+
+        assert_eq!(found, vec![
+            (Path::new("config").join("product.json"), "Mirrors[1].Url"),
+            (package("a"), "DisplayName"),
+            (package("b"), "Name"),
+            (package("c"), "Script"),
+            (package("d"), "InstallPath"),
+        ], "{:#?}", problems);
+    }
+
+    #[test]
+    fn test_collect_strings() {
+        let value = serde_json::json!({ "Name": "app", "Mirrors": [{ "Url": "a/", "Weight": 1 }] });
+        let mut strings = vec![];
+        collect_strings(&value, String::new(), &mut strings);
+
+        strings.sort();
+        assert_eq!(strings, [("Mirrors[0].Url".to_owned(), "a/"), ("Name".to_owned(), "app")]);
+    }
+}
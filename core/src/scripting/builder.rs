@@ -45,6 +45,19 @@ impl IJSRuntime {
     }
 }
 
+/// Compiles 'src' in a throwaway context without running any of it, reporting syntax errors
+pub fn check_syntax(src: &str) -> Result<(), IJSError> {
+    let rt = Runtime::new().map_err(|err| IJSError::Execution(format!("{err:?}")))?;
+    let ctx = Context::full(&rt).map_err(|err| IJSError::Execution(format!("{err:?}")))?;
+
+    ctx.with(|ctx| {
+        // a function body is compiled when it's declared but only runs when it's called
+        ctx.eval::<(), _>(format!("(function() {{\n{}\n}});", src))
+            .catch(&ctx)
+            .map_err(|err| IJSError::Execution(format!("{err:?}")))
+    })
+}
+
 impl IJSContext {
     pub fn new(rt: &IJSRuntime, app: InstallyApp, target_package: Option<&Package>) -> Self {
         let rt_ref = rt.get_runtime();
@@ -1,14 +1,14 @@
 
-use std::fmt::{Display, Formatter};
+use std::{cmp::Ordering, fmt::{Display, Formatter}};
 
 use crate::*;
 use crate::definitions::script::ScriptOptional;
 use crate::extensions::future::FutureSyncExt;
 use crate::definitions::context::AppWrapper;
-use crate::helpers::file::IoError;
+use crate::helpers::{file::IoError, versioning::version_compare};
 
 use async_trait::async_trait;
-use definitions::error::PackageInstallError;
+use definitions::{error::PackageInstallError, journal::JournaledWorkload, resolver};
 use rust_i18n::error::{Error, ErrorDetails};
 
 use super::workload::Workload;
@@ -17,8 +17,8 @@ pub type InstallerWrapper = AppWrapper<InstallerOptions>;
 
 #[derive(Clone)]
 pub struct InstallerOptions {
-    /// Names of the packages to install. `None` installs the repository's
-    /// default packages.
+    /// Names of the packages or package groups to install. `None` installs the
+    /// repository's default packages.
     pub target_packages: Option<Vec<String>>,
 }
 
@@ -37,17 +37,23 @@ impl Default for InstallerOptions {
 #[async_trait]
 impl Workload for InstallerWrapper {
     async fn run(&mut self) -> Result<(), Error> {
+        self.app.begin_transaction(JournaledWorkload::Installer { target_packages: self.settings.target_packages.clone() })?;
         self.install().wait()?;
         Ok(())
     }
 
     async fn finalize(&mut self, has_error: bool) -> Result<(), Error> {
 
-        // all went ok. persist any change has been made.
-        if !has_error {
-            self.app.persist_summary();
+        // nothing is left half done, summary is back to where it was before the workload
+        if has_error {
+            self.app.rollback_transaction();
+            return Ok(());
         }
 
+        // all went ok. persist any change has been made, the journal is only dropped afterwards
+        self.app.persist_summary();
+        self.app.commit_transaction();
+
         Ok(())
     }
 }
@@ -71,34 +77,69 @@ impl InstallerWrapper {
 
         self.app.dump_product_to_installation_directory(None)?;
 
-        let repository = self.app.get_repository();
-        let targets = match &self.settings.target_packages {
-            None => repository.get_default_packages(),
-            Some(names) => names.iter()
-                .filter_map(|name| match repository.get_package(name) {
-                    Some(package) => Some(package),
-                    None => {
-                        log::warn!("Package '{}' not found in repository, skipping.", name);
-                        None
-                    }
-                })
-                .collect()
+        let channel = self.app.get_channel();
+        let repository = &self.app.get_channel_repository(&channel)?;
+        log::info!("Installing from release channel '{}'", channel);
+
+        let (targets, groups) = match &self.settings.target_packages {
+            None => (repository.get_default_packages(), Vec::new()),
+            Some(names) => repository.select(names),
         };
 
-        log::info!("Packages in installition queue: {}", targets.iter().map(|e| e.display_name.clone()).collect::<Vec<_>>().join(", "));
+        // pull in missing dependencies, ordered so that dependencies are installed first.
+        // version bounds, conflicts and replacements are checked before anything is downloaded
+        let summary = self.app.get_summary();
+        let resolution = resolver::install_order(repository, summary.get_packages(), &targets)?;
 
-        for package in targets {  
+        log::info!("Packages in installition queue: {}", resolution.order.iter().map(|e| e.display_name.clone()).collect::<Vec<_>>().join(", "));
+
+        // fail before anything is downloaded or removed if either volume can't hold the queue
+        let mut pending = Vec::new();
+        for package in &resolution.order {
+            match summary.find(package) {
+                Some(installed) if version_compare(&installed.version, &package.version)? == Ordering::Equal => {}
+                _ => pending.push(package.clone()),
+            }
+        }
+        self.app.ensure_disk_space(&pending)?;
+
+        for installation in &resolution.replaced {
+            log::info!("Removing {} as it's replaced by a package in installition queue.", installation.display_name);
+            self.app.set_workload_state(InstallerWorkloadState::RemovingReplacedComponent(installation.display_name.clone()));
+            self.app.uninstall_package(installation).wait()?;
+        }
+
+        for package in resolution.order {  
+            let installed = summary.find(&package);
+            if let Some(installed) = installed {
+                if version_compare(&installed.version, &package.version)? == Ordering::Equal {
+                    log::info!("Package '{}' is already installed, version: {}. Skipping.", package.name, package.version);
+                    continue;
+                }
+            }
 
             log::info!("Starting to install {}, version: {}.", package.display_name, package.version);
             log::info!("Downloading the package file from {}", &self.app.get_product().get_uri_to_package(&package));
             self.app.set_workload_state(InstallerWorkloadState::DownloadingComponent(package.display_name.clone()));
             let package_file = self.app.download_package(&package).await?;
 
+            // installed version does not satisfy a dependent's version bounds
+            if let Some(installed) = installed {
+                log::info!("Removing installed version {} of {} to satisfy version requirements.", installed.version, installed.display_name);
+                self.app.set_workload_state(InstallerWorkloadState::RemovingReplacedComponent(installed.display_name.clone()));
+                self.app.uninstall_package_for_update(installed).wait()?;
+            }
+
             log::info!("Installing, package {}", &package.display_name);
             self.app.set_workload_state(InstallerWorkloadState::InstallingComponent(package.display_name.clone()));
             self.app.install_package(&package_file).wait()?; // TODO: make err types send
         }
 
+        self.app.modify_summary(|summary| {
+            summary.channel = Some(channel);
+            summary.add_groups(&groups);
+        });
+
         // means app is doing fresh installition. workload is not invoked by ffi api
         // or via maintinancetool
         if std::env::var("STANDALONE_EXECUTION").is_ok() {
@@ -117,6 +158,7 @@ impl InstallerWrapper {
 pub enum InstallerWorkloadState {
     FetchingRemoteTree(String),
     DownloadingComponent(String),
+    RemovingReplacedComponent(String),
     InstallingComponent(String),
     Interrupted(ErrorDetails),
     Aborted,
@@ -133,6 +175,9 @@ impl Display for InstallerWorkloadState {
             InstallerWorkloadState::DownloadingComponent(s) => {
                 write!(f, "{:?}", t!("states.downloadingX", [s]))
             }, 
+            InstallerWorkloadState::RemovingReplacedComponent(s) => {
+                write!(f, "{:?}", t!("states.removing-replaced-packageX", [s]))
+            },
             InstallerWorkloadState::InstallingComponent(s) => {
                 write!(f, "{:?}", t!("states.installingX", [s]))
             },
@@ -5,4 +5,6 @@ pub mod workload;
 pub mod installer;
 pub mod uninstaller;
 pub mod updater;
+pub mod verifier;
+pub mod repairer;
 pub mod noop;
\ No newline at end of file
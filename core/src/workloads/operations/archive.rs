@@ -1,14 +1,52 @@
-use std::ops::{Deref, DerefMut};
+use std::{cmp::Reverse, io::ErrorKind, ops::{Deref, DerefMut}, path::{Path, PathBuf}};
 
 use serde::{Deserialize, Serialize};
 use crate::{archiving::{self, error::ArchiveError}, *};
-use self::{definitions::{app::InstallyApp, dependency::PackageFile, operation::{OperationPerformer, OperationRecord}, package::Package}, helpers::serializer};
+use self::{definitions::{app::InstallyApp, dependency::PackageFile, operation::{OperationKind, OperationPerformer, OperationRecord}, package::Package, verification::FileIssue}, helpers::{checksum::{Checksum, ChecksumAlgorithm, ChecksumError}, file::IoError, serializer::{self, SerializationError}}};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct ExtractArchiveOperationInner {
     destination: String,
     files: Vec<std::path::PathBuf>,
+
+    /// Size and checksum of the extracted files. Empty for packages installed before these were recorded,
+    /// only presence of their files can be verified
+    #[serde(default)]
+    digests: Vec<FileDigest>,
+
+    /// Configuration files the user changed, left as they were. The new version of each is extracted next to it as '<file>.new'.
+    /// These aren't created by the operation, so they aren't removed on revert either
+    #[serde(default)]
+    preserved: Vec<PathBuf>,
+
+    /// Directories the extraction created, parents first. An empty path is the package directory itself
+    #[serde(default)]
+    directories: Vec<PathBuf>,
+}
+
+/// Appended to the new version of a configuration file the user changed
+pub const NEW_CONFIG_FILE_SUFFIX: &str = ".new";
+
+/// Size and checksum of an extracted file, path is relative to the package directory
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct FileDigest {
+    pub path: PathBuf,
+    pub size: u64,
+    pub checksum: Checksum,
+}
+
+impl FileDigest {
+    /// Takes size and checksum of 'path' under 'directory'
+    pub fn generate(directory: &Path, path: &Path) -> Result<Self, ChecksumError> {
+        // installed files might be read-only
+        let mut file = std::fs::File::open(directory.join(path)).map_err(IoError::from)?;
+        let size = file.metadata().map_err(IoError::from)?.len();
+        let checksum = Checksum::generate_file(ChecksumAlgorithm::default(), &mut file)?;
+
+        Ok(FileDigest { path: path.to_path_buf(), size, checksum })
+    }
 }
 
 pub struct ExtractArchiveOperation<'a> {
@@ -25,6 +63,9 @@ impl<'a> ExtractArchiveOperation<'a> {
             inner: ExtractArchiveOperationInner {
                 destination: destination.to_owned(),
                 files: Vec::new(),
+                digests: Vec::new(),
+                preserved: Vec::new(),
+                directories: Vec::new(),
             },
         }
     }
@@ -34,6 +75,59 @@ impl<'a> ExtractArchiveOperation<'a> {
         let inner: ExtractArchiveOperationInner = serializer::from_json(weak_struct)?;
         Ok(ExtractArchiveOperation { archive: None, target: package, inner })
     }
+
+    /// Compares the extracted files with what they were right after extraction. Files without a recorded digest are only checked to exist.
+    /// Configuration files are expected to be changed by the user, only their absence is reported
+    pub fn verify(&self, app: &InstallyApp) -> Vec<FileIssue> {
+        let package_directory = app.get_product().get_path_to_package(&self.target);
+
+        self.files.iter()
+            .filter_map(|file| self.check_file(&package_directory, file))
+            .filter(|issue| !matches!(issue, FileIssue::Modified(path) if self.is_config_file(path)))
+            .collect()
+    }
+
+    /// Gets the configuration files that no longer are the way they were extracted. Ones without a recorded digest
+    /// can't be told apart from the shipped version, they are taken as changed so that the user's copy is never lost
+    pub fn get_modified_config_files(&self, app: &InstallyApp) -> Vec<PathBuf> {
+        let package_directory = app.get_product().get_path_to_package(&self.target);
+
+        self.files.iter().chain(self.preserved.iter())
+            .filter(|file| self.is_config_file(file))
+            .filter_map(|file| match self.check_file(&package_directory, file) {
+                Some(FileIssue::Modified(path)) => Some(path),
+                None if !self.digests.iter().any(|d| &d.path == file) => Some(file.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn is_config_file(&self, file: &Path) -> bool {
+        self.target.config_files.iter().any(|pattern| helpers::glob::matches_path(pattern, file))
+    }
+
+    fn check_file(&self, package_directory: &Path, file: &Path) -> Option<FileIssue> {
+        let digest = self.digests.iter().find(|d| d.path == file);
+        match (package_directory.join(file).is_file(), digest) {
+            (false, _) => Some(FileIssue::Missing(file.to_path_buf())),
+            (true, None) => None,
+            (true, Some(digest)) => match FileDigest::generate(package_directory, file) {
+                Ok(actual) if actual.size == digest.size && actual.checksum == digest.checksum => None,
+                Ok(_) => Some(FileIssue::Modified(file.to_path_buf())),
+                Err(err) => {
+                    log::warn!("Failed to read {:?} of {} package, treating it as modified. {:?}", file, self.target.display_name, err);
+                    Some(FileIssue::Modified(file.to_path_buf()))
+                }
+            }
+        }
+    }
+}
+
+/// Gets where the new version of the configuration file 'path' is extracted to when the user's is kept
+fn new_config_file_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(NEW_CONFIG_FILE_SUFFIX);
+    PathBuf::from(name)
 }
 
 impl Serialize for ExtractArchiveOperation<'_> {
@@ -54,32 +148,105 @@ impl OperationPerformer for ExtractArchiveOperation<'_> {
         let package_file = self.archive.unwrap();
         let progress_closure = app.create_progress_closure();   
 
-        let files = {
+        // configuration files the user changed, kept by the removal of the previous version
+        let kept = app.take_kept_config_files(&self.target.name);
+        let redirect = |path: &Path| match kept.iter().any(|k| k == path) {
+            true => new_config_file_path(path),
+            false => path.to_path_buf(),
+        };
+
+        let extraction = {
             let mut archive = package_file.handle.lock();
-            archiving::zip_read::extract_to(
+            archiving::zip_read::extract_redirected_to(
                 archive.as_file_mut(),
                 &product.get_path_to_package(&self.target),
+                &redirect,
                 &progress_closure,
-                Some(&package_file.sha1)
+                package_file.checksum.as_ref()
             )
                 .map_err(|err| ArchiveError::from(err))?
         };
 
-        self.files = files;
+        let package_directory = product.get_path_to_package(&self.target);
+        self.digests = extraction.files.iter()
+            .map(|file| FileDigest::generate(&package_directory, file))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // digest of the shipped version is what tells whether the user changed the file by the next update
+        for file in kept {
+            let shipped = self.digests.iter().find(|d| d.path == new_config_file_path(&file)).cloned();
+            if let Some(shipped) = shipped {
+                log::info!("Kept changed configuration file {:?} of {} package, new version is extracted next to it.", file, self.target.display_name);
+                self.digests.push(FileDigest { path: file.clone(), ..shipped });
+                self.preserved.push(file);
+            }
+        }
+
+        self.files = extraction.files;
+        self.directories = extraction.directories;
+
+        // directories holding kept files outlived the previous version, they are this one's to remove now
+        let has_own_directory = package_directory != product.get_relative_target_directory();
+        let inherited = self.preserved.iter()
+            .flat_map(|file| file.ancestors().skip(1))
+            .filter(|directory| has_own_directory || !directory.as_os_str().is_empty())
+            .map(|directory| directory.to_path_buf())
+            .collect::<Vec<_>>();
+
+        for directory in inherited.into_iter().rev() {
+            if !self.directories.contains(&directory) {
+                self.directories.push(directory);
+            }
+        }
+
         Ok(())
     }
 
     fn revert(&mut self, app: &InstallyApp) -> Result<(), rust_i18n::error::Error> {
-        let product = app.get_product();
+        // files are recorded relative to the package directory
+        let package_directory = app.get_product().get_path_to_package(&self.target);
+        // changed configuration files are left in place when the package is removed for an update
+        let kept = app.get_kept_config_files(&self.target.name);
 
-        self.files.iter().into_iter().for_each(|file| {
-            if let Err(err) = std::fs::remove_file(file.clone()) {
-                log::error!("Failed to delete {:?}. It's included inside {} package. Trace: {}", file.clone(), &self.target.display_name, err);
-            } else {
-                log::trace!("Deleted {:?} of {} package.", file.clone(), &self.target.display_name);
+        let mut leftovers = Vec::new();
+
+        self.files.iter().filter(|file| !kept.contains(*file)).map(|file| package_directory.join(file)).for_each(|file| {
+            match app.remove_file(&file) {
+                Ok(()) => log::trace!("Deleted {:?} of {} package.", file, &self.target.display_name),
+                Err(err) => {
+                    log::error!("Failed to delete {:?}. It's included inside {} package. Trace: {:?}", file, &self.target.display_name, err);
+                    if !matches!(&err, SerializationError::Io(err) if err.kind() == ErrorKind::NotFound) {
+                        leftovers.push(file);
+                    }
+                }
             }
         });
 
+        // deepest first so that parents are emptied before their turn. directories holding anything else, such as files
+        // the user created, are left in place
+        let mut directories = self.directories.iter()
+            .filter(|directory| !kept.iter().any(|file| file.starts_with(directory)))
+            .collect::<Vec<_>>();
+        directories.sort_by_key(|directory| Reverse(directory.components().count()));
+
+        for directory in directories.into_iter().map(|directory| package_directory.join(directory)) {
+            match app.remove_dir(&directory) {
+                Ok(()) => log::trace!("Deleted directory {:?} of {} package.", directory, &self.target.display_name),
+                Err(SerializationError::Io(err)) if err.kind() == ErrorKind::NotFound => (),
+                Err(err) if hand_over_directory(app, &self.target, &directory) => {
+                    log::info!("Left directory {:?} of {} package in place, it's handed over to the other packages having files in it. {:?}", directory, &self.target.display_name, err);
+                }
+                Err(err) => {
+                    log::warn!("Left directory {:?} of {} package in place. {:?}", directory, &self.target.display_name, err);
+                    leftovers.push(directory);
+                }
+            }
+        }
+
+        if !leftovers.is_empty() {
+            app.modify_summary(|summary| summary.add_leftovers(&leftovers));
+        }
+
         Ok(())
     }
 
@@ -100,6 +267,50 @@ impl OperationPerformer for ExtractArchiveOperation<'_> {
     }
 }
 
+/// Hands 'directory' over to an installed package other than 'target' that has files under it, so that it's removed along
+/// with that package instead. Returns false if there is no such package or the directory isn't inside its package directory
+fn hand_over_directory(app: &InstallyApp, target: &Package, directory: &Path) -> bool {
+    let product = app.get_product();
+
+    app.modify_summary(|summary| {
+        for installation in summary.packages.iter_mut().filter(|installation| installation.name != target.name) {
+            let package = installation.to_package();
+            let package_directory = product.get_path_to_package(&package);
+            let Ok(relative) = directory.strip_prefix(&package_directory) else {
+                continue;
+            };
+
+            for record in installation.operations.get_records_mut().iter_mut() {
+                if *record.get_kind() != OperationKind::ExtractArchiveOperation {
+                    continue;
+                }
+
+                let Ok(mut operation) = ExtractArchiveOperation::from_record(Some(package.clone()), record) else {
+                    continue;
+                };
+
+                if !operation.files.iter().any(|file| file.starts_with(relative)) {
+                    continue;
+                }
+
+                if !operation.directories.iter().any(|owned| owned == relative) {
+                    operation.directories.push(relative.to_path_buf());
+                }
+
+                match operation.as_weak_struct() {
+                    Ok(payload) => {
+                        *record = OperationRecord::new(OperationKind::ExtractArchiveOperation, payload);
+                        return true;
+                    }
+                    Err(err) => log::warn!("Failed to hand directory {:?} over to {} package. {:?}", directory, package.display_name, err),
+                }
+            }
+        }
+
+        false
+    })
+}
+
 impl Deref for ExtractArchiveOperation<'_> {
     type Target = ExtractArchiveOperationInner;
 
@@ -111,4 +322,194 @@ impl DerefMut for ExtractArchiveOperation<'_> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.inner
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use crate::definitions::{operation::OperationHistory, product::Product, summary::PackageInstallation};
+    use super::*;
+
+    fn installation(name: &str, directory: &Path, files: &[&str], directories: &[&str]) -> PackageInstallation {
+        let inner = ExtractArchiveOperationInner {
+            destination: String::new(),
+            files: files.iter().map(PathBuf::from).collect(),
+            digests: Vec::new(),
+            preserved: Vec::new(),
+            directories: directories.iter().map(PathBuf::from).collect(),
+        };
+
+        let mut operations = OperationHistory::default();
+        operations.add(OperationRecord::new(OperationKind::ExtractArchiveOperation, serializer::to_json(&inner).unwrap()));
+
+        PackageInstallation {
+            name: name.to_owned(),
+            display_name: name.to_owned(),
+            install_path: directory.to_str().unwrap().to_owned(),
+            operations,
+            ..Default::default()
+        }
+    }
+
+    fn revert(app: &InstallyApp, name: &str) {
+        let installation = app.get_summary().get_packages().iter().find(|p| p.name == name).cloned().unwrap();
+        let record = &installation.operations.get_records()[0];
+        ExtractArchiveOperation::from_record(Some(installation.to_package()), record).unwrap().revert(app).unwrap();
+        app.modify_summary(|summary| summary.packages.retain(|p| p.name != name));
+    }
+
+    fn operation(directory: &Path, files: &[&str]) -> ExtractArchiveOperation<'static> {
+        let target = Package {
+            name: "app".to_owned(),
+            display_name: "app".to_owned(),
+            install_path: directory.to_str().unwrap().to_owned(),
+            config_files: vec!["*.cfg".to_owned()],
+            ..Default::default()
+        };
+
+        let inner = ExtractArchiveOperationInner {
+            destination: String::new(),
+            files: files.iter().map(PathBuf::from).collect(),
+            digests: files.iter().map(|file| FileDigest::generate(directory, Path::new(file)).unwrap()).collect(),
+            preserved: Vec::new(),
+            directories: Vec::new(),
+        };
+
+        ExtractArchiveOperation { target, archive: None, inner }
+    }
+
+    #[test]
+    fn test_file_digest() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("file.txt"), "content").unwrap();
+
+        let digest = FileDigest::generate(dir.path(), Path::new("file.txt")).unwrap();
+        assert_eq!(digest.path, PathBuf::from("file.txt"));
+        assert_eq!(digest.size, 7);
+        assert_eq!(digest, FileDigest::generate(dir.path(), Path::new("file.txt")).unwrap());
+
+        std::fs::write(dir.path().join("file.txt"), "changed").unwrap();
+        assert_ne!(digest.checksum, FileDigest::generate(dir.path(), Path::new("file.txt")).unwrap().checksum);
+        assert!(FileDigest::generate(dir.path(), Path::new("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn test_check_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("intact.txt"), "intact").unwrap();
+        std::fs::write(dir.path().join("modified.txt"), "modified").unwrap();
+        std::fs::write(dir.path().join("missing.txt"), "missing").unwrap();
+
+        let mut operation = operation(dir.path(), &["intact.txt", "modified.txt", "missing.txt"]);
+        operation.files.push(PathBuf::from("untracked.txt"));
+        std::fs::write(dir.path().join("untracked.txt"), "untracked").unwrap();
+
+        std::fs::write(dir.path().join("modified.txt"), "changed!").unwrap();
+        std::fs::remove_file(dir.path().join("missing.txt")).unwrap();
+
+        assert_eq!(operation.check_file(dir.path(), Path::new("intact.txt")), None);
+        assert_eq!(operation.check_file(dir.path(), Path::new("modified.txt")), Some(FileIssue::Modified(PathBuf::from("modified.txt"))));
+        assert_eq!(operation.check_file(dir.path(), Path::new("missing.txt")), Some(FileIssue::Missing(PathBuf::from("missing.txt"))));
+
+        // installed before digests were recorded, only presence is checked
+        assert_eq!(operation.check_file(dir.path(), Path::new("untracked.txt")), None);
+    }
+
+    #[test]
+    fn test_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        for file in ["app.bin", "lib.bin", "app.cfg", "other.cfg"] {
+            std::fs::write(dir.path().join(file), file).unwrap();
+        }
+
+        let operation = operation(dir.path(), &["app.bin", "lib.bin", "app.cfg", "other.cfg"]);
+        let app = InstallyApp::default_with_product(&Product::default());
+        assert!(operation.verify(&app).is_empty());
+
+        std::fs::write(dir.path().join("app.bin"), "changed").unwrap();
+        std::fs::remove_file(dir.path().join("lib.bin")).unwrap();
+        std::fs::write(dir.path().join("app.cfg"), "changed").unwrap();
+        std::fs::remove_file(dir.path().join("other.cfg")).unwrap();
+
+        // configuration files are expected to be changed, only their absence is an issue
+        assert_eq!(operation.verify(&app), vec![
+            FileIssue::Modified(PathBuf::from("app.bin")),
+            FileIssue::Missing(PathBuf::from("lib.bin")),
+            FileIssue::Missing(PathBuf::from("other.cfg")),
+        ]);
+    }
+
+    #[test]
+    fn test_update_keeps_changed_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let installed = dir.path().join("installed");
+        std::fs::create_dir(&installed).unwrap();
+        std::fs::write(installed.join("app.bin"), "1").unwrap();
+        std::fs::write(installed.join("app.cfg"), "user").unwrap();
+
+        // installed before digests were recorded, the user might have changed it
+        let mut previous = operation(&installed, &["app.bin", "app.cfg"]);
+        previous.digests.clear();
+
+        let app = InstallyApp::default_with_product(&Product::default());
+        let kept = previous.get_modified_config_files(&app);
+        assert_eq!(kept, vec![PathBuf::from("app.cfg")]);
+
+        app.keep_config_files("app", kept);
+        previous.revert(&app).unwrap();
+        assert!(!installed.join("app.bin").exists());
+        assert_eq!(std::fs::read_to_string(installed.join("app.cfg")).unwrap(), "user");
+
+        let source = dir.path().join("source");
+        std::fs::create_dir(&source).unwrap();
+        std::fs::write(source.join("app.bin"), "2").unwrap();
+        std::fs::write(source.join("app.cfg"), "shipped").unwrap();
+
+        let archive = tempfile::NamedTempFile::new().unwrap();
+        archiving::zip_write::compress_dir(source.as_path(), archive.path(), zip::CompressionMethod::Stored, None, false).unwrap();
+        let package_file = PackageFile { handle: Arc::new(Mutex::new(archive)), checksum: None, package: previous.target.clone() };
+
+        let mut next = ExtractArchiveOperation::new(&package_file, "");
+        next.execute(&app).unwrap();
+
+        assert_eq!(std::fs::read_to_string(installed.join("app.bin")).unwrap(), "2");
+        assert_eq!(std::fs::read_to_string(installed.join("app.cfg")).unwrap(), "user");
+        assert_eq!(std::fs::read_to_string(installed.join("app.cfg.new")).unwrap(), "shipped");
+        assert_eq!(next.preserved, vec![PathBuf::from("app.cfg")]);
+        assert!(app.get_kept_config_files("app").is_empty());
+
+        // still not the shipped version, kept by the next update as well
+        assert_eq!(next.get_modified_config_files(&app), vec![PathBuf::from("app.cfg")]);
+    }
+
+    #[test]
+    fn test_revert_shared_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("shared")).unwrap();
+        std::fs::write(dir.path().join("shared/a.txt"), "a").unwrap();
+        std::fs::write(dir.path().join("shared/b.txt"), "b").unwrap();
+
+        let app = InstallyApp::default_with_product(&Product::default());
+        app.modify_summary(|summary| {
+            summary.packages.push(installation("a", dir.path(), &["shared/a.txt"], &["shared"]));
+            summary.packages.push(installation("b", dir.path(), &["shared/b.txt"], &[]));
+        });
+
+        // 'b' still has files in it, the directory is its to remove now
+        revert(&app, "a");
+        assert!(!dir.path().join("shared/a.txt").exists());
+        assert!(dir.path().join("shared/b.txt").exists());
+        assert!(app.get_summary().leftovers.is_empty());
+
+        let summary = app.get_summary();
+        let record = &summary.get_packages()[0].operations.get_records()[0];
+        let operation = ExtractArchiveOperation::from_record(Some(summary.get_packages()[0].to_package()), record).unwrap();
+        assert_eq!(operation.directories, vec![PathBuf::from("shared")]);
+
+        revert(&app, "b");
+        assert!(!dir.path().join("shared").exists());
+        assert!(app.get_summary().leftovers.is_empty());
+    }
+}
@@ -37,7 +37,7 @@ impl OperationPerformer for CreateFileOperation {
     }
 
     fn revert(&mut self, app: &crate::definitions::app::InstallyApp) -> Result<(), rust_i18n::error::Error> {
-        helpers::file::delete(&self.destination)?;
+        app.remove_file(&self.destination)?;
         Ok(())
     }
 
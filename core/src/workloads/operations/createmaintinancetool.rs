@@ -1,7 +1,7 @@
 
 use serde::{Deserialize, Serialize};
 
-use crate::{definitions::operation::OperationPerformer, helpers::{file::IoError, serializer}, *};
+use crate::{archiving::bundle, definitions::operation::OperationPerformer, helpers::{file::IoError, serializer}, *};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
@@ -29,6 +29,15 @@ impl OperationPerformer for CreateMaintenanceToolOperation {
 
     fn execute(&mut self, app: &crate::definitions::app::InstallyApp) -> Result<(), rust_i18n::error::Error> {
         crate::sys::create_maintenance_tool(app, &self.name)?;
+
+        // it's a copy of the setup binary, a bundled repository is of no use to it
+        let path = app.get_product().get_relative_target_directory().join(format!("{}{}", self.name, std::env::consts::EXE_SUFFIX));
+        match bundle::strip(&path) {
+            Ok(true) => log::info!("Removed the bundled repository from maintenance tool {:?}.", path),
+            Ok(false) => (),
+            Err(err) => log::warn!("Failed to remove the bundled repository from maintenance tool {:?}. {:?}", path, err),
+        }
+
         Ok(())
     }
 
@@ -0,0 +1,170 @@
+use std::fmt::{Formatter, Display};
+
+use async_trait::async_trait;
+use definitions::{error::PackageRepairError, journal::JournaledWorkload};
+use rust_i18n::error::{Error, ErrorDetails};
+
+use crate::*;
+use crate::extensions::future::FutureSyncExt;
+use crate::definitions::context::AppWrapper;
+use crate::helpers::file::IoError;
+use super::workload::Workload;
+
+pub type RepairerWrapper = AppWrapper<RepairerOptions>;
+
+#[derive(Clone)]
+pub struct RepairerOptions {
+    /// Names of the installed packages to repair. `None` repairs every
+    /// installed package. Only packages with missing or modified files are downloaded.
+    ///
+    /// Files are restored from the archive of the installed version. Once the repository
+    /// no longer lists that version, repair fails with `PackageRepairError::Unavailable`
+    /// and the package has to be updated instead, which restores its files as well.
+    pub target_packages: Option<Vec<String>>,
+}
+
+impl RepairerOptions {
+    pub fn new(target_packages: Option<Vec<String>>) -> Self {
+        RepairerOptions { target_packages }
+    }
+}
+
+impl Default for RepairerOptions {
+    fn default() -> Self {
+        RepairerOptions { target_packages: None }
+    }
+}
+
+#[async_trait]
+impl Workload for RepairerWrapper {
+    async fn run(&mut self) -> Result<(), Error> {
+        self.app.begin_transaction(JournaledWorkload::Repairer { target_packages: self.settings.target_packages.clone() })?;
+        self.repair().wait()?;
+        Ok(())
+    }
+
+    async fn finalize(&mut self, has_error: bool) -> Result<(), Error> {
+        // nothing is left half done, summary is back to where it was before the workload
+        if has_error {
+            self.app.rollback_transaction();
+            return Ok(());
+        }
+
+        // all went ok. persist any change has been made, the journal is only dropped afterwards
+        self.app.persist_summary();
+        self.app.commit_transaction();
+
+        Ok(())
+    }
+}
+
+impl RepairerWrapper {
+    pub(self) async fn repair(&self) -> Result<(), PackageRepairError> {
+        log::info!("Starting to repair {}", &self.app.get_product().name);
+        log::info!("Target directory {:?}", &self.app.get_product().get_relative_target_directory());
+
+        self.app.set_workload_state(RepairerWorkloadState::FetchingRemoteTree(self.app.get_product().name.clone()));
+        self.app.ensure_repository().await?;
+
+        let summary = self.app.get_summary();
+        if let Some(names) = &self.settings.target_packages {
+            if let Some(unknown) = names.iter().find(|name| !summary.packages.iter().any(|p| &p.name == *name)) {
+                return Err(PackageRepairError::InstallationNotFound(unknown.clone()));
+            }
+        }
+
+        let targets = summary.packages.iter()
+            .filter(|p| match &self.settings.target_packages {
+                Some(names) => names.contains(&p.name),
+                None => true
+            })
+            .collect::<Vec<_>>();
+
+        let mut report = Vec::new();
+        for installation in &targets {
+            self.app.set_workload_state(RepairerWorkloadState::VerifyingPackage(installation.display_name.clone()));
+            report.push(self.app.verify_package(installation)?);
+        }
+
+        let broken = report.iter().filter(|v| !v.is_intact()).collect::<Vec<_>>();
+        log::info!("Packages that will be repaired: {}", broken.iter().map(|e| e.display_name.clone()).collect::<Vec<_>>().join(", "));
+
+        if !broken.is_empty() {
+            helpers::process::terminate_processes_under_folder(self.app.get_product().get_relative_target_directory())
+                .map_err(|err| Error::from(IoError::from(err)))?;
+        }
+
+        // a package that can't be repaired doesn't keep the others from being repaired
+        let mut failure = None;
+        for verification in broken {
+            let Some(installation) = targets.iter().find(|i| i.name == verification.name) else {
+                log::error!("{} is verified but no longer installed, skipping.", verification.display_name);
+                failure.get_or_insert(PackageRepairError::InstallationNotFound(verification.name.clone()));
+                continue;
+            };
+
+            log::info!("Repairing {}, {} file(s) missing or modified.", verification.display_name, verification.issues.len());
+            self.app.set_workload_state(RepairerWorkloadState::RepairingComponent(verification.display_name.clone()));
+            if let Err(err) = self.app.repair_package(installation, verification).await {
+                log::error!("Failed to repair {}. {:?}", verification.display_name, err);
+                failure.get_or_insert(err);
+            }
+        }
+
+        // report what's left broken, if anything
+        let mut report = Vec::new();
+        for installation in &targets {
+            report.push(self.app.verify_package(installation)?);
+        }
+        self.app.set_verification(report);
+
+        if let Some(err) = failure {
+            return Err(err);
+        }
+
+        self.app.set_workload_state(RepairerWorkloadState::Done);
+        self.app.set_state_progress(100.0);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RepairerWorkloadState {
+    FetchingRemoteTree(String),
+    VerifyingPackage(String),
+    RepairingComponent(String),
+    Interrupted(ErrorDetails),
+    Done,
+}
+
+impl Display for RepairerWorkloadState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepairerWorkloadState::FetchingRemoteTree(s) => {
+                write!(f, "{:?}", t!("states.fetching-repositoryOfX", [s]))
+            },
+
+            RepairerWorkloadState::VerifyingPackage(s) => {
+                write!(f, "{:?}", t!("states.verifyingX", [s]))
+            },
+
+            RepairerWorkloadState::RepairingComponent(s) => {
+                write!(f, "{:?}", t!("states.repairingX", [s]))
+            },
+
+            RepairerWorkloadState::Interrupted(e) => {
+                write!(f, "{:?}", t!("states.interrupted.byX", [e.to_string()]))
+            },
+
+            RepairerWorkloadState::Done => {
+                write!(f, "{:?}", t!("states.completed"))
+            }
+        }
+    }
+}
+
+impl Default for RepairerWorkloadState {
+    fn default() -> Self {
+        Self::FetchingRemoteTree("".to_string())
+    }
+}
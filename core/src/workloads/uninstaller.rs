@@ -1,7 +1,7 @@
 use std::fmt::{Formatter, Display};
 
 use async_trait::async_trait;
-use definitions::error::PackageUninstallError;
+use definitions::{error::PackageUninstallError, journal::JournaledWorkload, package::Package, repository::Repository, resolver, summary::PackageInstallation};
 use rust_i18n::error::{Error, ErrorDetails};
 
 use crate::{definitions::script::ScriptOptional, extensions::future::FutureSyncExt, helpers::file::IoError};
@@ -15,30 +15,41 @@ pub type UninstallerWrapper = AppWrapper<UninstallerOptions>;
 #[derive(Clone)]
 pub struct UninstallerOptions {
     /// Names of the packages to remove. `None` removes every installed
-    /// package.
+    /// package. Required packages are only removed along with every other package.
     pub target_packages: Option<Vec<String>>,
+
+    /// Also removes installed packages that depend on the targets. Otherwise
+    /// removal of a package that is still required is refused.
+    pub cascade: bool,
 }
 
 impl UninstallerOptions {
-    pub fn new(target_packages: Option<Vec<String>>) -> Self {
-        UninstallerOptions { target_packages }
+    pub fn new(target_packages: Option<Vec<String>>, cascade: bool) -> Self {
+        UninstallerOptions { target_packages, cascade }
     }
 }
 
 impl Default for UninstallerOptions {
     fn default() -> Self {
-        UninstallerOptions { target_packages: None }
+        UninstallerOptions { target_packages: None, cascade: false }
     }
 }
 
 #[async_trait] 
 impl Workload for UninstallerWrapper {
     async fn run(&mut self) -> Result<(), Error> {
+        self.app.begin_transaction(JournaledWorkload::Uninstaller { target_packages: self.settings.target_packages.clone(), cascade: self.settings.cascade })?;
         self.uninstall().wait()?;
         Ok(())
     }
     
     async fn finalize(&mut self, has_error: bool) -> Result<(), Error> {
+        // nothing is left half done, summary is back to where it was before the workload
+        if has_error {
+            self.app.rollback_transaction();
+            return Ok(());
+        }
+
         let summary = self.app.get_summary();
 
         // no package is present, full uninstallation
@@ -50,10 +61,9 @@ impl Workload for UninstallerWrapper {
             }); 
         }
 
-        // all went ok. persist any change has been made.
-        if !has_error {
-            self.app.persist_summary();
-        }
+        // all went ok. persist any change has been made, the journal and the lock are only dropped afterwards
+        self.app.persist_summary();
+        self.app.commit_transaction();
 
         Ok(())
     }
@@ -86,6 +96,12 @@ impl UninstallerWrapper {
             }
         };
 
+        // dependents are removed before the packages they depend on
+        let targets = resolver::removal_order(&summary.packages, &targets, self.settings.cascade)?;
+
+        let repository = self.app.get_channel_repository(&self.app.get_channel()).ok();
+        check_required(&summary.packages, &targets, repository.as_ref())?;
+
         log::info!("Installed packages: {}", summary.packages.iter().map(|e| e.display_name.clone()).collect::<Vec<_>>().join(", ")); 
         log::info!("Packages that will be removed: {}", targets.iter().map(|e| e.display_name.clone()).collect::<Vec<_>>().join(", "));     
 
@@ -102,6 +118,24 @@ impl UninstallerWrapper {
     }
 }
 
+/// Refuses removal of 'targets' if one of them is required, either as installed or by the 'repository'.
+/// Required packages only go away along with the whole product
+fn check_required(installed: &[PackageInstallation], targets: &[PackageInstallation], repository: Option<&Repository>) -> Result<(), PackageUninstallError> {
+    let whole_product = installed.iter().all(|installed| targets.iter().any(|t| t.name == installed.name));
+    if whole_product {
+        return Ok(());
+    }
+
+    let required = targets.iter().find(|t| t.required || repository
+        .and_then(|repository| repository.get_package(&t.name))
+        .is_some_and(|p| p.required));
+
+    match required {
+        Some(required) => Err(PackageUninstallError::Required(required.name.clone())),
+        None => Ok(()),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum UninstallerWorkloadState {
     FetchingRemoteTree(String),
@@ -134,4 +168,28 @@ impl Default for UninstallerWorkloadState {
     fn default() -> Self {
         Self::Done
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn installation(name: &str, required: bool) -> PackageInstallation {
+        PackageInstallation { name: name.to_owned(), required, ..Default::default() }
+    }
+
+    #[test]
+    fn test_check_required() {
+        let installed = vec![installation("core", true), installation("docs", false), installation("tools", false)];
+
+        assert!(check_required(&installed, &installed[1..2], None).is_ok());
+        assert!(matches!(check_required(&installed, &installed[..2], None), Err(PackageUninstallError::Required(name)) if name == "core"));
+        assert!(check_required(&installed, &installed, None).is_ok());
+
+        // packages marked required after they were installed are refused as well
+        let mut repository = Repository::new("app", 0);
+        repository.packages = vec![Package { name: "tools".to_owned(), required: true, ..Default::default() }];
+        assert!(matches!(check_required(&installed, &installed[2..], Some(&repository)), Err(PackageUninstallError::Required(name)) if name == "tools"));
+        assert!(check_required(&installed, &installed[1..2], Some(&repository)).is_ok());
+    }
+}
@@ -1,13 +1,13 @@
 
-use std::fmt::{Formatter, Display};
+use std::{cmp::Ordering, fmt::{Formatter, Display}};
 
 use async_trait::async_trait;
-use definitions::error::PackageUpdateError;
+use definitions::{error::PackageUpdateError, journal::JournaledWorkload, package::Package, requirement::PackageRequirement, resolver};
 use rust_i18n::error::{Error, ErrorDetails};
 
 use crate::definitions::script::ScriptOptional;
 use crate::extensions::future::FutureSyncExt;
-use crate::helpers::file::IoError;
+use crate::helpers::{file::IoError, versioning::version_compare};
 use crate::*;
 use crate::definitions::context::AppWrapper;
 
@@ -17,37 +17,47 @@ pub type UpdaterWrapper = AppWrapper<UpdaterOptions>;
 
 #[derive(Clone)]
 pub struct UpdaterOptions {
-    /// Names of the packages to update. `None` updates every installed package
-    /// that is outdated.
+    /// Names of the packages or package groups to update. `None` updates every
+    /// installed package that is outdated.
     pub target_packages: Option<Vec<String>>,
+
+    /// Release channel to switch to. `None` stays on the installed channel.
+    /// Packages are updated or downgraded to the versions published on the new channel.
+    pub channel: Option<String>,
 }
 
 impl UpdaterOptions {
-    pub fn new(target_packages: Option<Vec<String>>) -> Self {
-        UpdaterOptions { target_packages }
+    pub fn new(target_packages: Option<Vec<String>>, channel: Option<String>) -> Self {
+        UpdaterOptions { target_packages, channel }
     }
 }
 
 impl Default for UpdaterOptions {
     fn default() -> Self {
-        UpdaterOptions { target_packages: None }
+        UpdaterOptions { target_packages: None, channel: None }
     }
 }
 
 #[async_trait]
 impl Workload for UpdaterWrapper {
     async fn run(&mut self) -> Result<(), Error> {
+        self.app.begin_transaction(JournaledWorkload::Updater { target_packages: self.settings.target_packages.clone(), channel: self.settings.channel.clone() })?;
         self.update().wait()?;
         Ok(())
     }
 
     async fn finalize(&mut self, has_error: bool) -> Result<(), Error> {
 
-        // all went ok. persist any change has been made.
-        if !has_error {
-            self.app.persist_summary();
+        // nothing is left half done, summary is back to where it was before the workload
+        if has_error {
+            self.app.rollback_transaction();
+            return Ok(());
         }
 
+        // all went ok. persist any change has been made, the journal is only dropped afterwards
+        self.app.persist_summary();
+        self.app.commit_transaction();
+
         Ok(())
     }
 }
@@ -64,25 +74,79 @@ impl UpdaterWrapper {
         let global = self.app.download_global_script().await?;
         global.if_exist(|s| Ok(s.invoke_before_update()?))?;
 
-        let repository = self.app.get_repository();
-        let state = summary.cross_check(&repository.packages);
+        let current = self.app.get_channel();
+        let channel = self.settings.channel.clone().unwrap_or(current.clone());
+        let repository = &self.app.get_channel_repository(&channel)?;
+        let state = summary.cross_check(&repository.packages)?;
+
+        // switching channels might bring older versions, those are treated as updates
+        let switching = channel != current;
+        let downgrades = if switching {
+            log::info!("Switching release channel from '{}' to '{}'", current, channel);
+            state.downgrades
+        } else {
+            vec![]
+        };
+
+        let targets = self.settings.target_packages.as_ref().map(|names| repository.expand_names(names));
+
+        // packages that joined an installed group after it was installed
+        let groups = summary.groups.iter()
+            .filter(|group| match &self.settings.target_packages {
+                Some(names) => names.contains(group),
+                None => true
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let joined = repository.get_joined_packages(&groups, &summary.packages);
 
         log::info!("Installed packages: {}", summary.packages.iter().map(|e| e.display_name.clone()).collect::<Vec<_>>().join(", "));
         log::info!("Packages that are outdated: {}", state.updates.iter().map(|e| e.local.display_name.clone()).collect::<Vec<_>>().join(", "));
+        log::info!("Packages that joined installed groups: {}", joined.iter().map(|e| e.display_name.clone()).collect::<Vec<_>>().join(", "));
 
-        if state.updates.len() != 0 {
+        if state.updates.len() != 0 || state.replacements.len() != 0 || downgrades.len() != 0 || joined.len() != 0 {
             helpers::process::terminate_processes_under_folder(self.app.get_product().get_relative_target_directory())
                 .map_err(|err| Error::from(IoError::from(err)))?;
         }
 
-        for pair in state.updates {
-            let local = pair.local;
-            let remote = pair.remote;
+        // check if package is opted-out specifically via start args
+        let pending = state.updates.into_iter()
+            .chain(downgrades.into_iter())
+            .chain(state.replacements.into_iter())
+            .filter(|pair| match &targets {
+                Some(targets) if !targets.iter().any(|name| name == &pair.local.name) => {
+                    log::info!("Skipping update of {} as it's not listed in target package list. Installed: {}, New: {}.", pair.local.display_name, pair.local.version, pair.remote.version);
+                    false
+                }
+                _ => true
+            })
+            .collect::<Vec<_>>();
+
+        // newer versions might depend on packages that are not installed yet. version bounds, conflicts
+        // and replacements are checked before anything is downloaded
+        let mut remotes = Vec::<Package>::new();
+        pending.iter()
+            .filter(|pair| !remotes.iter().any(|r| r.name == pair.remote.name))
+            .for_each(|pair| remotes.push(pair.remote.clone()));
+        joined.into_iter()
+            .for_each(|package| if !remotes.iter().any(|r| r.name == package.name) { remotes.push(package) });
+
+        let resolution = resolver::install_order(repository, summary.get_packages(), &remotes)?;
+
+        // fail before anything is downloaded or removed if either volume can't hold the updates
+        let mut downloads = Vec::new();
+        for remote in &resolution.order {
+            match summary.find(remote) {
+                Some(local) if version_compare(&local.version, &remote.version)? == Ordering::Equal => {}
+                _ => downloads.push(remote.clone()),
+            }
+        }
+        self.app.ensure_disk_space(&downloads)?;
 
-            // check if package is opted-out specifically via start args
-            if let Some(targets) = &self.settings.target_packages {
-                if !targets.iter().any(|name| name == &local.name) {
-                    log::info!("Skipping update of {} as it's not listed in target package list. Installed: {}, New: {}.", local.display_name, local.version, remote.version);
+        for remote in resolution.order {
+            let local = summary.find(&remote);
+            if let Some(local) = local {
+                if version_compare(&local.version, &remote.version)? == Ordering::Equal {
                     continue;
                 }
             }
@@ -91,16 +155,29 @@ impl UpdaterWrapper {
             self.app.set_workload_state(UpdaterWorkloadState::DownloadingComponent(remote.display_name.clone()));
             let update = self.app.download_package(&remote).wait()?;
 
-            log::info!("Removing old installation before the update, package {}", &remote.display_name);
-            self.app.set_workload_state(UpdaterWorkloadState::RemovingOutdatedComponent(remote.display_name.clone()));
-            self.app.uninstall_package(&local).await?;
+            let outdated = local.into_iter()
+                .chain(resolution.replaced.iter().filter(|replaced| remote.replaces.iter().any(|r| PackageRequirement::name_of(r) == replaced.name)));
+
+            for local in outdated {
+                log::info!("Removing old installation before the update, package {}", &local.display_name);
+                self.app.set_workload_state(UpdaterWorkloadState::RemovingOutdatedComponent(local.display_name.clone()));
+                // a replaced package is a different one, its configuration files aren't carried over
+                match local.name == remote.name {
+                    true => self.app.uninstall_package_for_update(local).await?,
+                    false => self.app.uninstall_package(local).await?,
+                }
+            }
     
             log::info!("Installing update, package {}", &remote.display_name);
             self.app.set_workload_state(UpdaterWorkloadState::InstallingComponent(remote.display_name.clone()));
             self.app.install_package(&update).await?;
         }
 
-        global.if_exist(|s| Ok(s.invoke_before_update()?))?;
+        if switching {
+            self.app.modify_summary(|summary| summary.channel = Some(channel));
+        }
+
+        global.if_exist(|s| Ok(s.invoke_after_update()?))?;
 
         self.app.set_workload_state(UpdaterWorkloadState::Done);
         self.app.set_state_progress(100.0);
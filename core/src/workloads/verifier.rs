@@ -0,0 +1,105 @@
+use std::fmt::{Formatter, Display};
+
+use async_trait::async_trait;
+use rust_i18n::error::{Error, ErrorDetails};
+
+use crate::*;
+use crate::definitions::context::AppWrapper;
+use super::workload::Workload;
+
+pub type VerifierWrapper = AppWrapper<VerifierOptions>;
+
+#[derive(Clone)]
+pub struct VerifierOptions {
+    /// Names of the installed packages to verify. `None` verifies every
+    /// installed package.
+    pub target_packages: Option<Vec<String>>,
+}
+
+impl VerifierOptions {
+    pub fn new(target_packages: Option<Vec<String>>) -> Self {
+        VerifierOptions { target_packages }
+    }
+}
+
+impl Default for VerifierOptions {
+    fn default() -> Self {
+        VerifierOptions { target_packages: None }
+    }
+}
+
+#[async_trait]
+impl Workload for VerifierWrapper {
+    async fn run(&mut self) -> Result<(), Error> {
+        self.verify()?;
+        Ok(())
+    }
+
+    async fn finalize(&mut self, has_error: bool) -> Result<(), Error> {
+        // nothing is changed, there is nothing to persist
+        Ok(())
+    }
+}
+
+impl VerifierWrapper {
+    pub(self) fn verify(&self) -> Result<(), Error> {
+        log::info!("Starting to verify {}", &self.app.get_product().name);
+        log::info!("Target directory {:?}", &self.app.get_product().get_relative_target_directory());
+
+        let summary = self.app.get_summary();
+        let targets = summary.packages.iter()
+            .filter(|p| match &self.settings.target_packages {
+                Some(names) => names.contains(&p.name),
+                None => true
+            })
+            .collect::<Vec<_>>();
+
+        let mut report = Vec::new();
+        for (i, installation) in targets.iter().enumerate() {
+            self.app.set_workload_state(VerifierWorkloadState::VerifyingPackage(installation.display_name.clone()));
+            let verification = self.app.verify_package(installation)?;
+
+            log::info!("{}", verification);
+            verification.issues.iter().for_each(|issue| log::warn!("{} {}", installation.name, issue));
+
+            report.push(verification);
+            self.app.set_state_progress((i + 1) as f32 / targets.len() as f32 * 100.0);
+        }
+
+        self.app.set_verification(report);
+        self.app.set_workload_state(VerifierWorkloadState::Done);
+        self.app.set_state_progress(100.0);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum VerifierWorkloadState {
+    VerifyingPackage(String),
+    Interrupted(ErrorDetails),
+    Done,
+}
+
+impl Display for VerifierWorkloadState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifierWorkloadState::VerifyingPackage(s) => {
+                write!(f, "{:?}", t!("states.verifyingX", [s]))
+            },
+
+            VerifierWorkloadState::Interrupted(e) => {
+                write!(f, "{:?}", t!("states.interrupted.byX", [e.to_string()]))
+            },
+
+            VerifierWorkloadState::Done => {
+                write!(f, "{:?}", t!("states.completed"))
+            }
+        }
+    }
+}
+
+impl Default for VerifierWorkloadState {
+    fn default() -> Self {
+        Self::Done
+    }
+}
@@ -0,0 +1,45 @@
+use std::path::Path;
+
+use clap::{Parser, Subcommand};
+use instally_core::lint;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct Opt {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Validates the configuration and package definitions, reporting every problem found
+    Lint {
+        // Source folder for packages
+        #[arg(short, long, default_value = "source")]
+        source: String,
+
+        // Configuration folder
+        #[arg(short, long, default_value = "config")]
+        config: String,
+    },
+}
+
+fn main() {
+    let rust_log = std::env::var("RUST_LOG").unwrap_or("info".into()); 
+    std::env::set_var("RUST_LOG", rust_log);  
+    env_logger::init();
+
+    let opt = Opt::parse();
+
+    match opt.command {
+        Command::Lint { source, config } => {
+            let problems = lint::lint_source(Path::new(&config), Path::new(&source));
+            problems.iter().for_each(|problem| log::error!("{}", problem));
+            log::info!("{} problem(s) found.", problems.len());
+
+            if !problems.is_empty() {
+                std::process::exit(1);
+            }
+        }
+    }
+}
@@ -1,8 +1,8 @@
 
-use std::{path::Path, fs};
+use std::{fs::{self, OpenOptions}, io::Write, path::{Path, PathBuf}};
 
 use clap::Parser;
-use instally_core::{archiving, definitions::{package::{Package, PackageDefinition}, product::Product, repository::Repository}, helpers::serializer};
+use instally_core::{archiving, lint, definitions::{changelog::Changelog, package::{Package, PackageDefinition}, product::Product, repository::{Channel, Repository}}, helpers::{checksum::{Checksum, ChecksumAlgorithm}, platform, serializer, signature}};
 use walkdir::WalkDir;
 
 #[derive(Parser, Debug)]
@@ -19,6 +19,30 @@ pub struct Opt {
     // Configuration folder
     #[arg(short, long, default_value = "config")]
     config: String,
+
+    // Ed25519 secret key used to sign repository.json, defaults to 'secret.key' inside the configuration folder
+    #[arg(short = 'k', long)]
+    secret_key: Option<String>,
+
+    // Checksum algorithm of package archives, sha256 or blake3
+    #[arg(long, default_value = "sha256", value_parser = parse_checksum_algorithm)]
+    checksum: ChecksumAlgorithm,
+
+    // Oldest installer version able to install from the generated repository
+    #[arg(long)]
+    minimum_installer_version: Option<String>,
+
+    // Installer binary published with the repository, outdated maintenance tools replace themselves with it
+    #[arg(long)]
+    installer: Option<String>,
+
+    // Generates a new key pair into the configuration folder and exits
+    #[arg(long)]
+    generate_keys: bool,
+
+    // Refuses to generate if the configuration or package definitions have problems, see 'instally lint'
+    #[arg(long)]
+    lint: bool,
 }
 
 fn main() {
@@ -27,14 +51,45 @@ fn main() {
     env_logger::init();
 
     let opt = Opt::parse();
+    let algorithm = opt.checksum;
     
     let source_dir = Path::new(&opt.source);
     let target_dir = Path::new(&opt.output);
     let config_dir = Path::new(&opt.config);
     let product_path = config_dir.join("product.json");
+    let secret_key_path = opt.secret_key.map(|p| Path::new(&p).to_path_buf()).unwrap_or(config_dir.join("secret.key"));
+
+    if opt.generate_keys {
+        // overwriting a key pair in use would lock installed products out of every later repository
+        let public_key_path = config_dir.join("public.key");
+        if let Some(existing) = [&secret_key_path, &public_key_path].into_iter().find(|path| path.exists()) {
+            log::error!("{:?} exists already, remove the key pair first to generate a new one.", existing);
+            std::process::exit(1);
+        }
+
+        let (secret_key, public_key) = signature::generate_keypair().unwrap();
+        if let Err(err) = write_new(&secret_key_path, &secret_key).and_then(|_| write_new(&public_key_path, &public_key)) {
+            log::error!("failed to write the key pair. {:?}", err);
+            std::process::exit(1);
+        }
+
+        log::info!("key pair generated, keep {:?} private. public key is embedded by binarygen.", &secret_key_path);
+        return;
+    }
+
+    // a broken configuration is refused up front, rather than failing halfway through
+    if opt.lint {
+        let problems = lint::lint_source(config_dir, source_dir);
+        if !problems.is_empty() {
+            problems.iter().for_each(|problem| log::error!("{}", problem));
+            log::error!("{} problem(s) found, repository is not generated.", problems.len());
+            std::process::exit(1);
+        }
+    }
 
     let tmp_product = Product::read_template(product_path).unwrap();
     let mut repository = Repository::new(&tmp_product.name, 0);
+    repository.minimum_installer_version = opt.minimum_installer_version.unwrap_or_default();
 
     let repository_packages_dir = target_dir.join("packages");
     std::fs::create_dir_all(repository_packages_dir.clone()).unwrap();
@@ -46,37 +101,55 @@ fn main() {
         let package_folder = package_folder.unwrap();
 
         let package_dir = package_folder.path();
-        let data_dir = package_dir.join("data");
         let meta_dir = package_dir.join("meta");
    
         let package_meta_path = meta_dir.join("package.json");
         let package_definition = PackageDefinition::from_file(&package_meta_path).unwrap();
 
-        let archive_name = format!("{}{}.zip", package_definition.name,  package_definition.version);
-        let archive_path = repository_packages_dir.join(&archive_name);
-        let mut sha1 = String::new();
-
-        log::info!("compressing {:?} package, destination {:?}", &package_definition.name, &archive_path);
-
-        archiving::zip_write::compress_dir(
-            &data_dir,
-            &archive_path,
-            zip::CompressionMethod::Bzip2,
-            Some(&mut sha1),
-            true
-        ).unwrap();
-        
-        let size = fs::metadata(&archive_path).unwrap().len();
-        let package = Package::from_definition(&package_definition, &archive_name, size, &sha1, &package_definition.script);
-        repository.packages.push(package.clone());
-        repository.size += size;
-
-        if !package.script.is_empty() {
-            let package_script_path = meta_dir.join(package.script.clone());
-            std::fs::copy(package_script_path, repository_packages_dir.join(package.script)).unwrap();
+        let release_notes = publish_release_notes(&meta_dir, &package_definition, &repository_packages_dir);
+
+        for (data_dir, os, arch) in platform_sources(package_dir, &package_definition) {
+            let archive_name = if os.is_empty() && arch.is_empty() {
+                format!("{}{}.zip", package_definition.name, package_definition.version)
+            } else {
+                format!("{}{}-{}-{}.zip", package_definition.name, package_definition.version, os, arch)
+            };
+            let archive_path = repository_packages_dir.join(&archive_name);
+            let mut checksum = Checksum::new(algorithm, "");
+
+            log::info!("compressing {:?} package, destination {:?}", &package_definition.name, &archive_path);
+
+            archiving::zip_write::compress_dir(
+                &data_dir,
+                &archive_path,
+                zip::CompressionMethod::Bzip2,
+                Some(&mut checksum),
+                true
+            ).unwrap();
+            
+            let size = fs::metadata(&archive_path).unwrap().len();
+            let mut package = Package::from_definition(&package_definition, &archive_name, size, &checksum, &package_definition.script);
+            package.os = os;
+            package.arch = arch;
+            package.installed_size = installed_size(&data_dir);
+            package.release_notes = release_notes.clone();
+            repository.size += size;
+
+            match package_definition.channel.as_str() {
+                "" => repository.packages.push(package.clone()),
+                channel => match repository.channels.iter_mut().find(|c| c.name == channel) {
+                    Some(listed) => listed.packages.push(package.clone()),
+                    None => repository.channels.push(Channel { name: channel.to_owned(), packages: vec![package.clone()] }),
+                }
+            }
+
+            log::info!("package file {:?} with checksum {} created.", &archive_name, checksum);
         }
 
-        log::info!("package file {:?} with sha1 {:?} created.", &archive_name, sha1);
+        if !package_definition.script.is_empty() {
+            let package_script_path = meta_dir.join(package_definition.script.clone());
+            std::fs::copy(package_script_path, repository_packages_dir.join(package_definition.script)).unwrap();
+        }
     }
 
     if !tmp_product.script.is_empty() {
@@ -85,8 +158,107 @@ fn main() {
         std::fs::copy(global_script_path, target_dir.join(tmp_product.script)).unwrap();
     }
 
+    if let Some(installer) = opt.installer {
+        let installer_path = Path::new(&installer);
+        let installer_name = installer_path.file_name().unwrap().to_str().unwrap().to_owned();
+        std::fs::copy(installer_path, target_dir.join(&installer_name)).unwrap();
+
+        let checksum = Checksum::generate(algorithm, installer_path).unwrap();
+        log::info!("installer {:?} with checksum {} published.", &installer_name, checksum);
+
+        repository.installer = installer_name;
+        repository.installer_checksum = Some(checksum);
+    }
+
     let repository_meta = serializer::to_json(&repository).unwrap();
-    std::fs::write(target_dir.join("repository.json"), repository_meta).unwrap(); // TODO: create consts for file names
+    std::fs::write(target_dir.join("repository.json"), &repository_meta).unwrap(); // TODO: create consts for file names
+
+    match std::fs::read_to_string(&secret_key_path) {
+        Ok(secret_key) => {
+            let signature = signature::sign(&secret_key, repository_meta.as_bytes()).unwrap();
+            std::fs::write(target_dir.join("repository.json.sig"), signature).unwrap();
+            log::info!("repository signed with {:?}", &secret_key_path);
+        }
+        Err(_) => log::warn!("secret key {:?} not found, repository is not signed.", &secret_key_path),
+    }
 
     log::info!("done");
 }
+
+/// Parses the checksum algorithm of the archives. SHA-1 is only read from older repositories, never generated
+fn parse_checksum_algorithm(value: &str) -> Result<ChecksumAlgorithm, String> {
+    match value.parse()? {
+        ChecksumAlgorithm::Sha1 => Err("sha1 is not accepted, use sha256 or blake3".to_owned()),
+        algorithm => Ok(algorithm),
+    }
+}
+
+/// Publishes 'CHANGELOG.md' of the meta folder, or the 'ReleaseNotes' field of the definition, next to the archives.
+/// Returns the published file name, empty if the package has no notes
+fn publish_release_notes(meta_dir: &Path, definition: &PackageDefinition, packages_dir: &Path) -> String {
+    let changelog = match fs::read_to_string(meta_dir.join("CHANGELOG.md")) {
+        Ok(markdown) => Changelog::parse(&markdown),
+        Err(_) if !definition.release_notes.is_empty() => Changelog::from_note(&definition.version, &definition.release_notes),
+        Err(_) => return String::new(),
+    };
+
+    let file_name = format!("{}{}.md", definition.name, definition.version);
+    std::fs::write(packages_dir.join(&file_name), changelog.to_markdown()).unwrap();
+
+    log::info!("release notes of {:?} published as {:?}", &definition.name, &file_name);
+    file_name
+}
+
+/// Gets the total size of the files under 'data_dir', which is what the package takes up once extracted
+fn installed_size(data_dir: &Path) -> u64 {
+    WalkDir::new(data_dir).into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Gets the data folders of a package along with the os and architecture they are built for.
+/// 'data' uses the platform of the definition, 'data-<os>-<arch>' folders override it
+fn platform_sources(package_dir: &Path, definition: &PackageDefinition) -> Vec<(PathBuf, String, String)> {
+    let mut sources = vec![];
+
+    let data_dir = package_dir.join("data");
+    if data_dir.is_dir() {
+        sources.push((data_dir, definition.os.clone(), definition.arch.clone()));
+    }
+
+    let mut entries = fs::read_dir(package_dir).unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|e| e.file_name());
+
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some((os, arch)) = name.strip_prefix("data-").and_then(|platform| platform.split_once('-')) else {
+            continue;
+        };
+
+        let (os, arch) = (platform::normalize_os(os), platform::normalize_arch(arch));
+        // installers pick a single build per package, a second one for the same platform would never be installed
+        if sources.iter().any(|(_, o, a)| platform::normalize_os(o) == os && platform::normalize_arch(a) == arch) {
+            log::warn!("{:?} targets the same platform as another data folder of package {:?}, skipping.", entry.path(), &definition.name);
+            continue;
+        }
+
+        sources.push((entry.path(), os, arch));
+    }
+
+    if sources.is_empty() {
+        log::warn!("no data folder found for package {:?}", &definition.name);
+    }
+
+    sources
+}
+
+/// Writes 'contents' to 'path', failing if it exists already
+fn write_new(path: &Path, contents: &str) -> std::io::Result<()> {
+    OpenOptions::new().write(true).create_new(true).open(path)?.write_all(contents.as_bytes())
+}
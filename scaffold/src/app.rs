@@ -16,24 +16,46 @@ use instally_core::{
     definitions::{
         app::InstallyApp, 
         context::AppContextNotifiable,
+        journal::InterruptedWorkload,
     },
+    factory::WorkloadKind,
     once_cell::sync::Lazy,
+    workloads::noop::NoopOptions,
 };
 use rust_i18n::t;
+use std::sync::Mutex;
 use tokio::sync::watch;
 
+use crate::factory;
+
 #[derive(Debug, Clone, PartialEq)]
 enum Msg {
     Win(Event),
     StateChanged,
+    /// Answer to the interrupted workload prompt, 'true' resumes it
+    Resolve(bool),
     Abort,
     Quit,
 }
 
+/// Interrupted workload awaiting an answer, 'requested' runs if it's rolled back
+struct Prompt {
+    interrupted: InterruptedWorkload,
+    requested: WorkloadKind,
+}
+
 static mut CURSOR: Option<Point> = None;
 
+static PROMPT: Lazy<Mutex<Option<Prompt>>> = Lazy::new(|| Mutex::new(None));
+
 pub static CH: Lazy<(watch::Sender<()>, watch::Receiver<()>)> = Lazy::new(|| watch::channel(()));
 
+/// Asks whether to resume 'interrupted' or to roll it back before any workload runs
+pub fn create_with_prompt(app: InstallyApp, interrupted: InterruptedWorkload, requested: WorkloadKind) -> iced::Result {
+    *PROMPT.lock().unwrap() = Some(Prompt { interrupted, requested });
+    create(app)
+}
+
 pub fn create(app: InstallyApp) -> iced::Result {
     app.get_context().lock().subscribe(Box::new(|_update| {
         let _ = CH.0.send(());
@@ -86,6 +108,14 @@ fn update(app: &mut InstallyApp, msg: Msg) -> Task<Msg> {
             },
             _ => {}
         },
+        Msg::Resolve(resume) => {
+            if let Some(prompt) = PROMPT.lock().unwrap().take() {
+                let workload = factory::resolve(app, prompt.interrupted, resume, prompt.requested)
+                    .unwrap_or_else(|err| WorkloadKind::Error(NoopOptions::default(), err.get_details().clone()));
+
+                _ = instally_core::factory::run(app.clone(), workload, None);
+            }
+        },
         Msg::Abort | Msg::Quit => std::process::exit(0),
         _ => {}
     }
@@ -98,7 +128,11 @@ fn view(app: &InstallyApp) -> Element<'_, Msg> {
     let binding = app.get_context();
     let ctx = binding.lock();
     let prog = ctx.get_progress();
-    let state = ctx.get_state_information();
+    let is_prompting = PROMPT.lock().unwrap().is_some();
+    let state = match is_prompting {
+        true => t!("prompts.resume-interrupted").to_string(),
+        false => ctx.get_state_information(),
+    };
     let has_error = ctx.get_result().is_some_and(|r| r.is_ok() == false);
     let is_complete = ctx.is_complete();
 
@@ -125,7 +159,7 @@ fn view(app: &InstallyApp) -> Element<'_, Msg> {
             .color(Color::from_rgb8(215, 215, 225))
             .align_x(Horizontal::Left),
         horizontal_space().width(Length::Fill),
-        action_button(is_complete),
+        actions(is_complete, is_prompting),
     ]
     .width(Length::Fill)
     .align_y(Alignment::Center)
@@ -185,7 +219,7 @@ fn view(app: &InstallyApp) -> Element<'_, Msg> {
             header,
             divider,
             status,
-            progress_section(prog),
+            if is_prompting { vertical_space().into() } else { progress_section(prog) },
             vertical_space().height(Length::Fill),
             bottom
         ]
@@ -254,6 +288,19 @@ fn progress_section(progress: f32) -> Element<'static, Msg> {
         .into()
 }
 
+fn actions(is_complete: bool, is_prompting: bool) -> Element<'static, Msg> {
+    if !is_prompting {
+        return action_button(is_complete).into();
+    }
+
+    row![
+        button(text(t!("rollback"))).style(rounded_primary).on_press(Msg::Resolve(false)),
+        button(text(t!("resume"))).style(rounded_primary).on_press(Msg::Resolve(true)),
+    ]
+    .spacing(8)
+    .into()
+}
+
 fn action_button(is_complete: bool) -> button::Button<'static, Msg> {
     let button = button(text(if is_complete { t!("ok") } else { t!("abort") })).style(rounded_primary);
 
@@ -275,7 +322,10 @@ fn desired_window_size(app: &InstallyApp) -> Size {
     let product = app.get_product();
     let binding = app.get_context();
     let ctx = binding.lock();
-    let state = ctx.get_state_information();
+    let state = match PROMPT.lock().unwrap().is_some() {
+        true => t!("prompts.resume-interrupted").to_string(),
+        false => ctx.get_state_information(),
+    };
 
     let width = desired_window_width(product.product_url.as_str());
     let mut height = 210.0_f32;
@@ -1,5 +1,5 @@
 
-use instally_core::{definitions::{app::InstallyApp, product::Product}, factory::{Executor, WorkloadKind}, workloads::noop::NoopOptions};
+use instally_core::{definitions::{app::InstallyApp, journal::InterruptedWorkload, product::Product}, factory::{Executor, WorkloadKind}, workloads::noop::NoopOptions};
 
 use crate::app;
 
@@ -15,4 +15,17 @@ pub fn run(app: InstallyApp, settings: WorkloadKind, do_spawn_ui: bool) -> Execu
 
 pub fn failed(product: &Product, err: rust_i18n::error::Error) -> Executor {
     run(InstallyApp::default_with_product(product), WorkloadKind::Error(NoopOptions::default(), err.get_details().clone()), true)
+}
+
+/// Asks whether to resume 'interrupted' or to roll it back and run 'requested', the chosen workload is run once answered
+pub fn prompt(app: InstallyApp, interrupted: InterruptedWorkload, requested: WorkloadKind) {
+    app::create_with_prompt(app, interrupted, requested).expect("failed to spawn iced gui");
+}
+
+/// Resumes 'interrupted' or rolls it back, getting the workload to run
+pub fn resolve(app: &InstallyApp, interrupted: InterruptedWorkload, resume: bool, requested: WorkloadKind) -> Result<WorkloadKind, rust_i18n::error::Error> {
+    match resume {
+        true => interrupted.resume(app),
+        false => interrupted.rollback(app).map(|_| requested),
+    }
 }
\ No newline at end of file
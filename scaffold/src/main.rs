@@ -3,7 +3,7 @@
 
 use core::panic;
 use std::{backtrace::Backtrace, thread, time::Duration};
-use instally_core::{definitions::{app::InstallyApp, product::Product}, factory::WorkloadKind, helpers::serializer, workloads::{installer::InstallerOptions, uninstaller::UninstallerOptions, updater::UpdaterOptions}};
+use instally_core::{archiving::bundle::Bundle, definitions::{app::InstallyApp, product::Product}, factory::WorkloadKind, helpers::{serializer, workflow::Workflow}, workloads::{installer::InstallerOptions, repairer::RepairerOptions, uninstaller::UninstallerOptions, updater::UpdaterOptions}};
 
 mod factory;
 mod app;
@@ -90,10 +90,49 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    // a bundled repository is only used for fresh installations. the maintenance tool is copied from the setup binary
+    // with the bundle removed, should that fail it still updates from the remote repository
+    let app = match helpers::workflow::get_workflow_from_env() {
+        Workflow::FreshInstallition => match Bundle::open_current() {
+            Ok(Some(bundle)) => app.with_bundle(bundle),
+            Ok(None) => app,
+            Err(err) => {
+                log::error!("Failed to open the bundled repository, installing from the remote one. {:?}", err);
+                app
+            }
+        },
+        _ => app
+    };
+
     let args = parse_args();
+
+    // files of a workload cut short by a crash or power loss aren't recorded by the summary. the user is asked whether
+    // to resume it or to roll it back before anything else runs. silent runs resume it with '--resume', roll it back otherwise
+    let workload = match app.get_interrupted_workload() {
+        Some(interrupted) if !args.silent => {
+            log::warn!("Previous {:?} workload was interrupted after {} change(s).", interrupted.workload, interrupted.steps.len());
+            factory::prompt(app, interrupted, args.workload_type);
+
+            log::info!("Exit(0)");
+            return Ok(());
+        }
+        Some(interrupted) => {
+            log::warn!("Previous {:?} workload was interrupted after {} change(s).", interrupted.workload, interrupted.steps.len());
+            match factory::resolve(&app, interrupted, args.resume, args.workload_type) {
+                Ok(workload) => workload,
+                Err(err) => {
+                    _ = factory::failed(&product, err);
+                    log::info!("Exit(0)");
+                    return Ok(());
+                }
+            }
+        }
+        None => args.workload_type,
+    };
+
     _ = factory::run(
         app,
-        args.workload_type,
+        workload,
         !args.silent
     ).handle.await;
 
@@ -106,6 +145,7 @@ struct Args {
     workload_type: WorkloadKind,
     silent: bool,
     debug: bool,
+    resume: bool,
 }
 
 fn parse_args() -> Args {
@@ -113,6 +153,9 @@ fn parse_args() -> Args {
     let mut command = None;
     let mut silent = false;
     let mut debug = false;
+    let mut resume = false;
+    let mut cascade = false;
+    let mut channel: Option<String> = None;
     let mut target_packages: Option<Vec<String>> = None;
 
     while let Some(arg) = args.next() {
@@ -127,6 +170,13 @@ fn parse_args() -> Args {
             },
             "--silent" => silent = true,
             "--debug" => debug = true,
+            "--resume" => resume = true,
+            "--cascade" => cascade = true,
+            "--channel" => channel = args.next(),
+            // a group is selected just like a package, it's resolved against the repository
+            "--group" => if let Some(group) = args.next() {
+                target_packages.get_or_insert_with(Vec::new).push(group);
+            },
             "--packages" => {
                 args.by_ref().take_while(|a| !a.starts_with('-')).for_each(|a| {
                     target_packages.get_or_insert_with(Vec::new).push(a);
@@ -138,15 +188,17 @@ fn parse_args() -> Args {
 
     let workload = match command.unwrap_or("/install".to_owned()).as_str() {
         "/install" => WorkloadKind::Installer(InstallerOptions::new(target_packages)),
-        "/uninstall" => WorkloadKind::Uninstaller(UninstallerOptions::new(target_packages)),
-        "/update" => WorkloadKind::Updater(UpdaterOptions::new(target_packages)),
+        "/uninstall" => WorkloadKind::Uninstaller(UninstallerOptions::new(target_packages, cascade)),
+        "/update" => WorkloadKind::Updater(UpdaterOptions::new(target_packages, channel)),
+        "/repair" => WorkloadKind::Repairer(RepairerOptions::new(target_packages)),
         _ => panic!("Unrecognized command!")
     };
 
     Args {
         workload_type: workload,
         silent,
-        debug
+        debug,
+        resume
     }
 }
 